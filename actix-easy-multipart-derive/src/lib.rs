@@ -12,6 +12,7 @@ use syn::{parse_macro_input, Type};
 struct MultipartFormAttrs {
     deny_unknown_fields: bool,
     duplicate_action: DuplicateAction,
+    accumulate_errors: bool,
 }
 
 #[derive(FromMeta)]
@@ -32,12 +33,27 @@ impl Default for DuplicateAction {
 struct FieldAttrs {
     rename: Option<String>,
     limit: Option<String>,
+    content_id: Option<String>,
+    position: Option<usize>,
+    root: bool,
+    content_types: Option<String>,
+    trim: Option<bool>,
+    normalize_newlines: Option<bool>,
+    normalize_unicode: Option<String>,
+    reject_control_chars: Option<bool>,
+    max_chars: Option<usize>,
+    empty_as_none: Option<bool>,
 }
 
 struct ParsedField<'t> {
     serialization_name: String,
     rust_name: &'t Ident,
     limit: Option<usize>,
+    content_id: Option<String>,
+    position: Option<usize>,
+    root: bool,
+    content_types: Option<Vec<String>>,
+    text_options: Option<proc_macro2::TokenStream>,
     ty: &'t Type,
 }
 
@@ -73,10 +89,68 @@ pub fn impl_multipart_form(input: proc_macro::TokenStream) -> proc_macro::TokenS
                 parse_size(&l).unwrap_or_else(|_| panic!("Unable to parse limit `{l}`")) as usize
             });
 
+            // The angle brackets around a Content-ID are optional
+            let content_id = attrs
+                .content_id
+                .map(|id| id.trim_start_matches('<').trim_end_matches('>').to_owned());
+
+            let content_types = attrs.content_types.map(|types| {
+                types
+                    .split(',')
+                    .map(|t| t.trim().to_owned())
+                    .filter(|t| !t.is_empty())
+                    .inspect(|t| {
+                        if t.parse::<mime::Mime>().is_err() {
+                            panic!("Unable to parse content type `{t}`")
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            });
+
+            // Set each text option given as an attribute
+            let mut text_options = quote!();
+            let mut set_option = |name: &str, value: Option<proc_macro2::TokenStream>| {
+                if let Some(value) = value {
+                    let name = Ident::new(name, proc_macro2::Span::call_site());
+                    text_options.extend(quote!(
+                        options.#name = ::std::option::Option::Some(#value);
+                    ));
+                }
+            };
+            set_option("trim", attrs.trim.map(|v| quote!(#v)));
+            set_option(
+                "normalize_newlines",
+                attrs.normalize_newlines.map(|v| quote!(#v)),
+            );
+            set_option(
+                "normalize_unicode",
+                attrs
+                    .normalize_unicode
+                    .map(|n| match n.to_lowercase().as_str() {
+                        "nfc" => quote!(::actix_easy_multipart::text::Normalization::Nfc),
+                        "nfkc" => quote!(::actix_easy_multipart::text::Normalization::Nfkc),
+                        _ => {
+                            panic!("Unknown Unicode normalization `{n}`, expected `nfc` or `nfkc`")
+                        }
+                    }),
+            );
+            set_option(
+                "reject_control_chars",
+                attrs.reject_control_chars.map(|v| quote!(#v)),
+            );
+            set_option("max_chars", attrs.max_chars.map(|v| quote!(#v)));
+            set_option("empty_as_none", attrs.empty_as_none.map(|v| quote!(#v)));
+            let text_options = (!text_options.is_empty()).then_some(text_options);
+
             Ok(ParsedField {
                 serialization_name,
                 rust_name,
                 limit,
+                content_id,
+                position: attrs.position,
+                root: attrs.root,
+                content_types,
+                text_options,
                 ty: &field.ty,
             })
         })
@@ -133,17 +207,146 @@ pub fn impl_multipart_form(input: proc_macro::TokenStream) -> proc_macro::TokenS
         }
     }
 
-    // from_state() implementation
-    let mut from_state_impl = quote!();
+    // match_part() implementation, the root part is matched first, then by Content-ID and then
+    // by position
+    let mut match_part_impl = quote!();
+    for field in parsed.iter().filter(|f| f.root) {
+        let name = &field.serialization_name;
+        match_part_impl.extend(quote!(
+            if part.root {
+                return ::std::option::Option::Some(#name);
+            }
+        ));
+    }
     for field in &parsed {
         let name = &field.serialization_name;
-        let rust_name = &field.rust_name;
-        let ty = &field.ty;
+        if let Some(content_id) = &field.content_id {
+            match_part_impl.extend(quote!(
+                if part.content_id == ::std::option::Option::Some(#content_id) {
+                    return ::std::option::Option::Some(#name);
+                }
+            ));
+        }
+    }
+    for field in &parsed {
+        let name = &field.serialization_name;
+        if let Some(position) = field.position {
+            match_part_impl.extend(quote!(
+                if part.index == #position {
+                    return ::std::option::Option::Some(#name);
+                }
+            ));
+        }
+    }
+    let match_part = if match_part_impl.is_empty() {
+        quote!()
+    } else {
+        quote!(
+            fn match_part(part: &::actix_easy_multipart::related::PartInfo) -> ::std::option::Option<&'static str> {
+                #match_part_impl
+                ::std::option::Option::None
+            }
+        )
+    };
+
+    // content_types() implementation
+    let mut content_types_impl = quote!();
+    for field in &parsed {
+        let name = &field.serialization_name;
+        if let Some(content_types) = &field.content_types {
+            content_types_impl.extend(quote!(
+                #name => ::std::option::Option::Some(&[#(#content_types),*]),
+            ));
+        }
+    }
+    let content_types = if content_types_impl.is_empty() {
+        quote!()
+    } else {
+        quote!(
+            fn content_types(field_name: &str) -> ::std::option::Option<&'static [&'static str]> {
+                match field_name {
+                    #content_types_impl
+                    _ => ::std::option::Option::None,
+                }
+            }
+        )
+    };
+
+    // text_options() implementation
+    let mut text_options_impl = quote!();
+    for field in &parsed {
+        let name = &field.serialization_name;
+        if let Some(text_options) = &field.text_options {
+            text_options_impl.extend(quote!(
+                #name => {
+                    let mut options = ::actix_easy_multipart::text::TextOptions::default();
+                    #text_options
+                    ::std::option::Option::Some(options)
+                }
+            ));
+        }
+    }
+    let text_options = if text_options_impl.is_empty() {
+        quote!()
+    } else {
+        quote!(
+            fn text_options(field_name: &str) -> ::std::option::Option<::actix_easy_multipart::text::TextOptions> {
+                match field_name {
+                    #text_options_impl
+                    _ => ::std::option::Option::None,
+                }
+            }
+        )
+    };
+
+    // from_state() implementation
+    let mut from_state_impl = quote!();
+    if attrs.accumulate_errors {
+        // Collect the result of every field before returning any errors
+        let mut results = quote!();
+        let mut values = quote!();
+        for field in &parsed {
+            let name = &field.serialization_name;
+            let rust_name = &field.rust_name;
+            let ty = &field.ty;
+            results.extend(quote!(
+                let #rust_name = <#ty as ::actix_easy_multipart::FieldGroupReader>::from_state(#name, &mut state)
+                    .map_err(|e| errors.push(e))
+                    .ok();
+            ));
+            values.extend(quote!(
+                #rust_name: #rust_name.unwrap(),
+            ));
+        }
         from_state_impl.extend(quote!(
-            #rust_name: <#ty as ::actix_easy_multipart::FieldGroupReader>::from_state(#name, &mut state)?,
+            let mut errors = ::std::vec::Vec::new();
+            #results
+            if !errors.is_empty() {
+                return ::std::result::Result::Err(::actix_easy_multipart::Error::Multiple(errors));
+            }
+            Ok(Self {
+                #values
+            })
+        ));
+    } else {
+        let mut values = quote!();
+        for field in &parsed {
+            let name = &field.serialization_name;
+            let rust_name = &field.rust_name;
+            let ty = &field.ty;
+            values.extend(quote!(
+                #rust_name: <#ty as ::actix_easy_multipart::FieldGroupReader>::from_state(#name, &mut state)?,
+            ));
+        }
+        from_state_impl.extend(quote!(
+            Ok(Self {
+                #values
+            })
         ));
     }
 
+    let accumulate_errors = attrs.accumulate_errors;
+
     let gen = quote! {
         impl ::actix_easy_multipart::MultipartFormTrait for #name {
             fn limit(field_name: &str) -> ::std::option::Option<usize> {
@@ -155,7 +358,7 @@ pub fn impl_multipart_form(input: proc_macro::TokenStream) -> proc_macro::TokenS
 
             fn handle_field<'t>(
                 req: &'t ::actix_web::HttpRequest,
-                field: ::actix_easy_multipart::actix_multipart::Field,
+                field: ::actix_easy_multipart::Field,
                 limits: &'t mut ::actix_easy_multipart::Limits,
                 state: &'t mut ::actix_easy_multipart::State,
             ) -> ::std::pin::Pin<::std::boxed::Box<dyn ::std::future::Future<Output = ::std::result::Result<(), ::actix_easy_multipart::Error>> + 't>> {
@@ -166,11 +369,19 @@ pub fn impl_multipart_form(input: proc_macro::TokenStream) -> proc_macro::TokenS
             }
 
             fn from_state(mut state: ::actix_easy_multipart::State) -> ::std::result::Result<Self, ::actix_easy_multipart::Error> {
-                Ok(Self {
-                    #from_state_impl
-                })
+                #from_state_impl
+            }
+
+            fn accumulate_errors() -> bool {
+                #accumulate_errors
             }
 
+            #match_part
+
+            #content_types
+
+            #text_options
+
         }
     };
     gen.into()
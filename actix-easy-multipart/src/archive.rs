@@ -0,0 +1,538 @@
+//! Reads a zip or tar.gz archive, enabled using the `archive` feature.
+use crate::tempfile::Tempfile;
+use crate::{Field, FieldReader, Limits};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, ResponseError};
+use derive_more::{Display, Error};
+use flate2::read::GzDecoder;
+use futures_core::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Write an archive to a temporary file on disk, like [`Tempfile`], and list its entries.
+///
+/// The format is detected from the data, and may be zip or gzip compressed tar. The archive is
+/// rejected if it exceeds any of the limits of the [`ArchiveConfig`], or if any entry is unsafe to
+/// extract: a path that is absolute or contains `..`, or a link or special file.
+///
+/// The entries are not extracted, use [`Archive::extract`] to do so. An entry that is itself an
+/// archive is not inspected, so nested archives must be checked separately if they are extracted.
+#[derive(Debug)]
+pub struct Archive {
+    /// The archive file.
+    pub file: Tempfile,
+    /// The format of the archive.
+    pub format: ArchiveFormat,
+    /// The files and directories in the archive.
+    pub entries: Vec<ArchiveEntry>,
+}
+
+/// The format of an [`Archive`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+/// An entry in an [`Archive`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ArchiveEntry {
+    /// The relative path of the entry.
+    pub path: PathBuf,
+    /// Whether the entry is a directory.
+    pub is_dir: bool,
+    /// The uncompressed size of the entry in bytes.
+    pub size: u64,
+}
+
+impl Archive {
+    /// Extract the archive into a directory, which is created if it does not exist.
+    ///
+    /// The directory should be empty, as existing files are overwritten. This performs blocking
+    /// I/O, so should be run using [`web::block`].
+    pub fn extract<P: AsRef<Path>>(&self, dir: P) -> Result<(), ArchiveError> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir).map_err(ArchiveError::Io)?;
+        let file = self.file.file.reopen().map_err(ArchiveError::Io)?;
+        let mut visitor = |entry: &ArchiveEntry, data: &mut dyn Read| {
+            let path = dir.join(&entry.path);
+            if entry.is_dir {
+                return fs::create_dir_all(&path);
+            }
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // The size was checked when the archive was read, so any more data is an error
+            let mut out = File::create(&path)?;
+            let written = io::copy(&mut data.take(entry.size + 1), &mut out)?;
+            if written != entry.size {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Entry size does not match its header",
+                ));
+            }
+            Ok(())
+        };
+        // The limits were checked when the archive was read, but the paths are checked again
+        let config = ArchiveConfig {
+            err_handler: None,
+            max_entries: usize::MAX,
+            max_size: u64::MAX,
+            max_compression_ratio: u64::MAX,
+            max_path_depth: usize::MAX,
+        };
+        let entries = EntryList::new(&config, self.file.size as u64);
+        let result = match self.format {
+            ArchiveFormat::Zip => read_zip(file, entries, Some(&mut visitor)),
+            ArchiveFormat::TarGz => read_tar_gz(file, entries, Some(&mut visitor)),
+        };
+        result.map(|_| ())
+    }
+}
+
+impl<'t> FieldReader<'t> for Archive {
+    type Future = LocalBoxFuture<'t, Result<Self, crate::Error>>;
+
+    fn read_field(req: &'t HttpRequest, field: Field, limits: &'t mut Limits) -> Self::Future {
+        async move {
+            let config = ArchiveConfig::from_req(req);
+            let field_name = field.name().to_owned();
+            let field_error = |err: ArchiveError| crate::Error::Field {
+                field_name: field_name.clone(),
+                source: config.map_error(req, err),
+            };
+
+            let file = Tempfile::read_field(req, field, limits).await?;
+            let reader = file
+                .file
+                .reopen()
+                .map_err(|e| field_error(ArchiveError::Io(e)))?;
+            let limits = ArchiveConfig {
+                err_handler: None,
+                ..*config
+            };
+            let size = file.size as u64;
+            let (format, entries) = web::block(move || read_entries(reader, size, &limits))
+                .await
+                .map_err(|e| crate::Error::Field {
+                    field_name: field_name.clone(),
+                    source: e.into(),
+                })?
+                .map_err(field_error)?;
+            Ok(Archive {
+                file,
+                format,
+                entries,
+            })
+        }
+        .boxed_local()
+    }
+
+    fn file_only() -> bool {
+        true
+    }
+}
+
+/// Called with each file entry and its data while reading an archive.
+type Visitor<'a> = &'a mut dyn FnMut(&ArchiveEntry, &mut dyn Read) -> io::Result<()>;
+
+/// Detect the format of the archive and list its entries, checking the limits.
+fn read_entries(
+    mut file: File,
+    size: u64,
+    config: &ArchiveConfig,
+) -> Result<(ArchiveFormat, Vec<ArchiveEntry>), ArchiveError> {
+    let mut magic = [0; 4];
+    let len = file.read(&mut magic).map_err(ArchiveError::Io)?;
+    file.rewind().map_err(ArchiveError::Io)?;
+    let entries = EntryList::new(config, size);
+    match &magic[..len] {
+        [b'P', b'K', 3, 4] | [b'P', b'K', 5, 6] => {
+            Ok((ArchiveFormat::Zip, read_zip(file, entries, None)?))
+        }
+        [0x1f, 0x8b, ..] => Ok((ArchiveFormat::TarGz, read_tar_gz(file, entries, None)?)),
+        _ => Err(ArchiveError::UnsupportedFormat),
+    }
+}
+
+fn read_zip(
+    file: File,
+    mut entries: EntryList,
+    mut visitor: Option<Visitor>,
+) -> Result<Vec<ArchiveEntry>, ArchiveError> {
+    let mut zip = zip::ZipArchive::new(BufReader::new(file)).map_err(zip_error)?;
+    for i in 0..zip.len() {
+        let entry = zip.by_index_raw(i).map_err(zip_error)?;
+        if entry.is_symlink() {
+            return Err(ArchiveError::UnsafeEntry(entry.name().to_owned()));
+        }
+        let is_dir = entry.is_dir();
+        let size = entry.size();
+        let name = entry.name().to_owned();
+        drop(entry);
+        let Some(entry) = entries.push(&name, is_dir, size)? else {
+            continue;
+        };
+        if let Some(visitor) = visitor.as_mut() {
+            let mut data = zip.by_index(i).map_err(zip_error)?;
+            visitor(entry, &mut data).map_err(ArchiveError::Io)?;
+        }
+    }
+    Ok(entries.entries)
+}
+
+fn read_tar_gz(
+    file: File,
+    mut entries: EntryList,
+    mut visitor: Option<Visitor>,
+) -> Result<Vec<ArchiveEntry>, ArchiveError> {
+    let mut tar = tar::Archive::new(GzDecoder::new(BufReader::new(file)));
+    for entry in tar.entries().map_err(tar_error)? {
+        let mut entry = entry.map_err(tar_error)?;
+        let name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+        let is_dir = match entry.header().entry_type() {
+            tar::EntryType::Regular | tar::EntryType::Continuous => false,
+            tar::EntryType::Directory => true,
+            tar::EntryType::XGlobalHeader => continue,
+            _ => return Err(ArchiveError::UnsafeEntry(name)),
+        };
+        let size = entry.size();
+        let Some(archive_entry) = entries.push(&name, is_dir, size)? else {
+            continue;
+        };
+        if let Some(visitor) = visitor.as_mut() {
+            visitor(archive_entry, &mut entry).map_err(ArchiveError::Io)?;
+        }
+    }
+    Ok(entries.entries)
+}
+
+/// The entries of an archive, which checks the limits as each entry is added so that an archive
+/// is rejected before the rest of it is decompressed.
+struct EntryList<'a> {
+    config: &'a ArchiveConfig,
+    /// The size of the archive file.
+    archive_size: u64,
+    entries: Vec<ArchiveEntry>,
+    total_size: u64,
+}
+
+impl<'a> EntryList<'a> {
+    fn new(config: &'a ArchiveConfig, archive_size: u64) -> Self {
+        Self {
+            config,
+            archive_size,
+            entries: Vec::new(),
+            total_size: 0,
+        }
+    }
+
+    /// Add an entry, returning `None` if it refers to the root directory.
+    fn push(
+        &mut self,
+        name: &str,
+        is_dir: bool,
+        size: u64,
+    ) -> Result<Option<&ArchiveEntry>, ArchiveError> {
+        let path = entry_path(name)?;
+        if path.as_os_str().is_empty() {
+            return Ok(None);
+        }
+        if path.components().count() > self.config.max_path_depth {
+            return Err(ArchiveError::TooDeep(name.to_owned()));
+        }
+        if self.entries.len() >= self.config.max_entries {
+            return Err(ArchiveError::TooManyEntries(self.config.max_entries));
+        }
+        self.total_size = self.total_size.saturating_add(size);
+        if self.total_size > self.config.max_size {
+            return Err(ArchiveError::TooLarge(self.config.max_size));
+        }
+        let max_ratio_size = self
+            .archive_size
+            .max(1)
+            .saturating_mul(self.config.max_compression_ratio);
+        if self.total_size > max_ratio_size {
+            return Err(ArchiveError::CompressionRatio);
+        }
+        self.entries.push(ArchiveEntry { path, is_dir, size });
+        Ok(self.entries.last())
+    }
+}
+
+/// Convert the name of an entry to a relative path, rejecting absolute paths and any that contain
+/// `..`.
+fn entry_path(name: &str) -> Result<PathBuf, ArchiveError> {
+    let unsafe_entry = || ArchiveError::UnsafeEntry(name.to_owned());
+    // Archives created on Windows may use backslashes
+    let normalized = name.replace('\\', "/");
+    if normalized.starts_with('/') || normalized.contains('\0') {
+        return Err(unsafe_entry());
+    }
+    let mut path = PathBuf::new();
+    for part in normalized.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => return Err(unsafe_entry()),
+            // A drive letter or other prefix on Windows
+            part if part.contains(':') => return Err(unsafe_entry()),
+            part => path.push(part),
+        }
+    }
+    Ok(path)
+}
+
+fn zip_error(err: zip::result::ZipError) -> ArchiveError {
+    ArchiveError::Invalid(err.to_string())
+}
+
+fn tar_error(err: io::Error) -> ArchiveError {
+    ArchiveError::Invalid(err.to_string())
+}
+
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum ArchiveError {
+    /// The data is not a zip or tar.gz archive
+    #[display(fmt = "Unsupported archive format")]
+    UnsupportedFormat,
+
+    /// The archive could not be read
+    #[display(fmt = "Invalid archive: {}", _0)]
+    Invalid(#[error(not(source))] String),
+
+    /// An entry has an absolute path or one containing `..`, or is a link or special file
+    #[display(fmt = "Unsafe archive entry: {}", _0)]
+    UnsafeEntry(#[error(not(source))] String),
+
+    /// The archive has more than the maximum number of entries
+    #[display(fmt = "Archive has more than {} entries", _0)]
+    TooManyEntries(#[error(not(source))] usize),
+
+    /// The total uncompressed size of the entries is larger than the maximum
+    #[display(fmt = "Archive is larger than {} bytes when uncompressed", _0)]
+    TooLarge(#[error(not(source))] u64),
+
+    /// The total uncompressed size of the entries is too large compared to the archive size
+    #[display(fmt = "Archive compression ratio is too high")]
+    CompressionRatio,
+
+    /// An entry's path has more than the maximum number of components
+    #[display(fmt = "Archive entry path is too deep: {}", _0)]
+    TooDeep(#[error(not(source))] String),
+
+    /// IO Error
+    #[display(fmt = "File I/O error: {}", _0)]
+    Io(io::Error),
+}
+
+impl ResponseError for ArchiveError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ArchiveError::UnsupportedFormat => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ArchiveError::Invalid(_) | ArchiveError::UnsafeEntry(_) | ArchiveError::TooDeep(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            ArchiveError::TooManyEntries(_)
+            | ArchiveError::TooLarge(_)
+            | ArchiveError::CompressionRatio => StatusCode::PAYLOAD_TOO_LARGE,
+            ArchiveError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Configuration for the [`Archive`] field reader.
+///
+/// The archive file itself is written using the [`TempfileConfig`](crate::tempfile::TempfileConfig).
+#[derive(Clone)]
+pub struct ArchiveConfig {
+    err_handler: Option<Arc<dyn Fn(ArchiveError, &HttpRequest) -> actix_web::Error + Send + Sync>>,
+    max_entries: usize,
+    max_size: u64,
+    max_compression_ratio: u64,
+    max_path_depth: usize,
+}
+
+const DEFAULT_CONFIG: ArchiveConfig = ArchiveConfig {
+    err_handler: None,
+    max_entries: 10_000,
+    max_size: 1_073_741_824, // 1 GiB
+    max_compression_ratio: 100,
+    max_path_depth: 32,
+};
+
+impl ArchiveConfig {
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(ArchiveError, &HttpRequest) -> actix_web::Error + Send + Sync + 'static,
+    {
+        self.err_handler = Some(Arc::new(f));
+        self
+    }
+
+    /// Extract payload config from app data. Check both `T` and `Data<T>`, in that order, and fall
+    /// back to the default payload config.
+    fn from_req(req: &HttpRequest) -> &Self {
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<web::Data<Self>>().map(|d| d.as_ref()))
+            .unwrap_or(&DEFAULT_CONFIG)
+    }
+
+    fn map_error(&self, req: &HttpRequest, err: ArchiveError) -> actix_web::Error {
+        if let Some(err_handler) = self.err_handler.as_ref() {
+            (*err_handler)(err, req)
+        } else {
+            err.into()
+        }
+    }
+
+    /// Sets the maximum number of entries, including directories, by default 10,000.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Sets the maximum total uncompressed size of the entries in bytes, by default 1 GiB.
+    pub fn max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Sets the maximum ratio of the total uncompressed size of the entries to the size of the
+    /// archive, by default 100.
+    pub fn max_compression_ratio(mut self, max_compression_ratio: u64) -> Self {
+        self.max_compression_ratio = max_compression_ratio;
+        self
+    }
+
+    /// Sets the maximum number of components in the path of an entry, by default 32. This only
+    /// applies to the paths of the entries, as nested archives are not inspected.
+    pub fn max_path_depth(mut self, max_path_depth: usize) -> Self {
+        self.max_path_depth = max_path_depth;
+        self
+    }
+}
+
+impl Default for ArchiveConfig {
+    fn default() -> Self {
+        DEFAULT_CONFIG
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::{Archive, ArchiveConfig};
+    use crate::MultipartForm;
+    use actix_web::http::StatusCode;
+    use actix_web::{web, App, HttpResponse, Responder};
+    use awc::Client;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::{Cursor, Write};
+    use std::path::Path;
+
+    fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            writer
+                .start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn tar_gz(entries: &[(&str, tar::EntryType, &[u8])]) -> Vec<u8> {
+        let encoder = GzEncoder::new(Vec::new(), Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        for (name, entry_type, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(*entry_type);
+            header.set_size(data.len() as u64);
+            if *entry_type == tar::EntryType::Symlink {
+                header.set_link_name("/etc/passwd").unwrap();
+            }
+            builder.append_data(&mut header, name, *data).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn body(data: &[u8]) -> Vec<u8> {
+        let mut body = b"--AaB03x\r\n\
+            Content-Disposition: form-data; name=\"archive\"; filename=\"a\"\r\n\
+            \r\n"
+            .to_vec();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n--AaB03x--\r\n");
+        body
+    }
+
+    #[derive(MultipartForm)]
+    struct TestArchive {
+        archive: Archive,
+    }
+
+    async fn test_archive_route(form: MultipartForm<TestArchive>) -> impl Responder {
+        assert_eq!(form.archive.entries.len(), 2);
+        assert_eq!(form.archive.entries[0].path, Path::new("a/b.txt"));
+
+        let dir = tempfile::tempdir().unwrap();
+        form.archive.extract(dir.path()).unwrap();
+        let data = std::fs::read(dir.path().join("a/b.txt")).unwrap();
+        assert_eq!(data, b"Hello, world!");
+        HttpResponse::Ok().body(format!("{:?}", form.archive.format))
+    }
+
+    #[actix_rt::test]
+    async fn test_archive() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_archive_route))
+                .app_data(ArchiveConfig::default().max_entries(2))
+        });
+        let send = |body: Vec<u8>| {
+            Client::default()
+                .post(srv.url("/"))
+                .content_type("multipart/form-data; boundary=AaB03x")
+                .send_body(body)
+        };
+
+        let archive = zip(&[("a/b.txt", b"Hello, world!"), ("c.txt", b"")]);
+        let mut response = send(body(&archive)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body().await.unwrap(), "Zip");
+
+        let archive = tar_gz(&[
+            ("a/b.txt", tar::EntryType::Regular, b"Hello, world!"),
+            ("c.txt", tar::EntryType::Regular, b""),
+        ]);
+        let mut response = send(body(&archive)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body().await.unwrap(), "TarGz");
+
+        let archive = zip(&[("../b.txt", b"Hello, world!")]);
+        let response = send(body(&archive)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let archive = tar_gz(&[("link", tar::EntryType::Symlink, b"")]);
+        let response = send(body(&archive)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let archive = zip(&[("a", b""), ("b", b""), ("c", b"")]);
+        let response = send(body(&archive)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // Exceeds the maximum compression ratio of 100
+        let archive = tar_gz(&[("a", tar::EntryType::Regular, &[0; 1_048_576])]);
+        let response = send(body(&archive)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let response = send(body(b"Hello, world!")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
@@ -1,6 +1,6 @@
 //! Reads a field into memory.
-use crate::{field_mime, Error, FieldReader, Limits};
-use actix_multipart::Field;
+use crate::scan::Content;
+use crate::{file_name, Error, Field, FieldReader, Limits};
 use actix_web::HttpRequest;
 use bytes::BytesMut;
 use futures_core::future::LocalBoxFuture;
@@ -14,28 +14,61 @@ pub struct Bytes {
     pub data: bytes::Bytes,
     /// The value of the `content-type` header.
     pub content_type: Option<Mime>,
-    /// The `filename` value in the `content-disposition` header.
+    /// The content type detected from the data, see [`Field::detected_content_type`].
+    pub detected_content_type: Option<Mime>,
+    /// The `filename*` or `filename` value in the `content-disposition` header.
     pub file_name: Option<String>,
+    /// The digests of the data, see [`DigestConfig`](crate::digest::DigestConfig).
+    #[cfg(feature = "digest")]
+    pub digests: crate::digest::Digests,
+}
+
+impl Bytes {
+    /// The filename, sanitized so that it is safe to use on disk, see [`file_name::sanitize`].
+    pub fn sanitized_file_name(&self) -> Option<String> {
+        self.file_name.as_deref().and_then(file_name::sanitize)
+    }
+}
+
+impl Bytes {
+    /// Read the field into memory, without running the content scanners. This is used by the field
+    /// readers that parse the data, which are not uploads to be scanned.
+    pub(crate) async fn read_unscanned(
+        req: &HttpRequest,
+        field: &mut Field,
+        limits: &mut Limits,
+    ) -> Result<Self, Error> {
+        #[cfg(feature = "digest")]
+        let mut hasher = crate::digest::Hasher::new(req, field, Default::default())?;
+        #[cfg(not(feature = "digest"))]
+        let _ = req;
+
+        let mut data = BytesMut::new();
+        while let Some(chunk) = field.try_next().await? {
+            limits.try_consume_limits(chunk.len(), true)?;
+            #[cfg(feature = "digest")]
+            hasher.update(&chunk);
+            data.extend(chunk);
+        }
+        Ok(Bytes {
+            data: data.freeze(),
+            content_type: field.content_type().cloned(),
+            detected_content_type: field.detected_content_type().cloned(),
+            file_name: field.file_name(),
+            #[cfg(feature = "digest")]
+            digests: hasher.finish()?,
+        })
+    }
 }
 
 impl<'t> FieldReader<'t> for Bytes {
     type Future = LocalBoxFuture<'t, Result<Self, Error>>;
 
-    fn read_field(_: &'t HttpRequest, mut field: Field, limits: &'t mut Limits) -> Self::Future {
+    fn read_field(req: &'t HttpRequest, mut field: Field, limits: &'t mut Limits) -> Self::Future {
         async move {
-            let mut data = BytesMut::new();
-            while let Some(chunk) = field.try_next().await? {
-                limits.try_consume_limits(chunk.len(), true)?;
-                data.extend(chunk);
-            }
-            Ok(Bytes {
-                data: data.freeze(),
-                content_type: field_mime(&field),
-                file_name: field
-                    .content_disposition()
-                    .get_filename()
-                    .map(str::to_owned),
-            })
+            let bytes = Bytes::read_unscanned(req, &mut field, limits).await?;
+            crate::scan::scan_field(req, &field, Content::Bytes(&bytes.data)).await?;
+            Ok(bytes)
         }
         .boxed_local()
     }
@@ -0,0 +1,191 @@
+//! Deserializes a field as CBOR, enabled using the `cbor` feature.
+use crate::bytes::Bytes;
+use crate::{is_format, is_valid_content_type, Field, FieldReader, Limits};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, ResponseError};
+use derive_more::{Deref, DerefMut, Display, Error};
+use futures_core::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+
+/// Deserialize from [CBOR](https://cbor.io/).
+#[derive(Debug, Deref, DerefMut)]
+pub struct Cbor<T: DeserializeOwned>(pub T);
+
+impl<T: DeserializeOwned> Cbor<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<'t, T: DeserializeOwned + 'static> FieldReader<'t> for Cbor<T> {
+    type Future = LocalBoxFuture<'t, Result<Self, crate::Error>>;
+
+    fn read_field(req: &'t HttpRequest, mut field: Field, limits: &'t mut Limits) -> Self::Future {
+        async move {
+            let config = CborConfig::from_req(req);
+            let field_name = field.name().to_owned();
+            let field_error = |err: CborFieldError| crate::Error::Field {
+                field_name: field_name.clone(),
+                source: config.map_error(req, err),
+            };
+
+            if !is_valid_content_type(&field, config.validate_content_type, |mime| {
+                is_format(mime, &["cbor"], "cbor")
+            }) {
+                return Err(field_error(CborFieldError::ContentType));
+            }
+
+            let bytes = Bytes::read_unscanned(req, &mut field, limits).await?;
+
+            let mut data = bytes.data.as_ref();
+            let value = ciborium::from_reader(&mut data)
+                .map_err(|e| field_error(CborFieldError::Deserialize(e)))?;
+            if !data.is_empty() {
+                return Err(field_error(CborFieldError::TrailingData));
+            }
+            Ok(Cbor(value))
+        }
+        .boxed_local()
+    }
+}
+
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum CborFieldError {
+    /// Deserialize error
+    #[display(fmt = "CBOR deserialize error: {}", _0)]
+    Deserialize(ciborium::de::Error<std::io::Error>),
+
+    /// Content type error
+    #[display(fmt = "Content type error")]
+    ContentType,
+
+    /// There is data after the CBOR value
+    #[display(fmt = "Trailing data after the CBOR value")]
+    TrailingData,
+}
+
+impl ResponseError for CborFieldError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// Configuration for the [`Cbor`] field reader.
+#[derive(Clone)]
+pub struct CborConfig {
+    err_handler:
+        Option<Arc<dyn Fn(CborFieldError, &HttpRequest) -> actix_web::Error + Send + Sync>>,
+    validate_content_type: bool,
+}
+
+const DEFAULT_CONFIG: CborConfig = CborConfig {
+    err_handler: None,
+    validate_content_type: true,
+};
+
+impl CborConfig {
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(CborFieldError, &HttpRequest) -> actix_web::Error + Send + Sync + 'static,
+    {
+        self.err_handler = Some(Arc::new(f));
+        self
+    }
+
+    /// Extract payload config from app data. Check both `T` and `Data<T>`, in that order, and fall
+    /// back to the default payload config.
+    fn from_req(req: &HttpRequest) -> &Self {
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<web::Data<Self>>().map(|d| d.as_ref()))
+            .unwrap_or(&DEFAULT_CONFIG)
+    }
+
+    fn map_error(&self, req: &HttpRequest, err: CborFieldError) -> actix_web::Error {
+        if let Some(err_handler) = self.err_handler.as_ref() {
+            (*err_handler)(err, req)
+        } else {
+            err.into()
+        }
+    }
+
+    /// Sets whether or not the field must have a valid `Content-Type` header to be parsed, which
+    /// has the `cbor` subtype (e.g. `application/cbor`) or the `+cbor` suffix.
+    pub fn validate_content_type(mut self, validate_content_type: bool) -> Self {
+        self.validate_content_type = validate_content_type;
+        self
+    }
+}
+
+impl Default for CborConfig {
+    fn default() -> Self {
+        DEFAULT_CONFIG
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cbor::{Cbor, CborConfig};
+    use crate::tests::send_form;
+    use crate::MultipartForm;
+    use actix_multipart_rfc7578::client::multipart;
+    use actix_web::http::StatusCode;
+    use actix_web::{web, App, HttpResponse, Responder};
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    #[derive(MultipartForm)]
+    struct CborForm {
+        metadata: Cbor<HashMap<String, u32>>,
+    }
+
+    async fn test_cbor_route(form: MultipartForm<CborForm>) -> impl Responder {
+        assert_eq!(form.metadata["temperature"], 21);
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_cbor() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_cbor_route))
+                .app_data(CborConfig::default())
+        });
+        let mut data = Vec::new();
+        ciborium::into_writer(&HashMap::from([("temperature", 21)]), &mut data).unwrap();
+        let form = |data: Vec<u8>, mime: &str| {
+            let mut form = multipart::Form::default();
+            let mime = mime.parse().unwrap();
+            form.add_reader_file_with_mime("metadata", Cursor::new(data), "", mime);
+            form
+        };
+
+        let response = send_form(&srv, form(data.clone(), "application/cbor"), "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Allow a structured syntax suffix
+        let response = send_form(&srv, form(data.clone(), "application/senml+cbor"), "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Deny because wrong content type
+        let response = send_form(&srv, form(data.clone(), "application/json"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Deny because there is a second item after the map
+        let mut trailing = data.clone();
+        ciborium::into_writer(&1, &mut trailing).unwrap();
+        let response = send_form(&srv, form(trailing, "application/cbor"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Deny because the map is truncated
+        let response = send_form(
+            &srv,
+            form(data[..data.len() - 1].to_vec(), "application/cbor"),
+            "/",
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,368 @@
+//! Deserializes the rows of a CSV field, enabled using the `csv` feature.
+use crate::{Field, FieldReader, Limits};
+use ::csv::ByteRecord;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, ResponseError};
+use csv_core::ReadRecordResult;
+use derive_more::{Deref, DerefMut, Display, Error};
+use futures_core::future::LocalBoxFuture;
+use futures_util::{FutureExt, TryStreamExt};
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+
+/// Deserialize each row of a CSV field.
+///
+/// The rows are parsed as the data arrives, so only the current row is buffered rather than the
+/// whole field. The field's data still counts towards the form's memory limit, as the deserialized
+/// rows are kept. By default the first row is a header row, which maps the columns to the fields
+/// of `T`, see [`CsvConfig::has_headers`].
+#[derive(Debug, Deref, DerefMut)]
+pub struct Csv<T: DeserializeOwned>(pub Vec<T>);
+
+impl<T: DeserializeOwned> Csv<T> {
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<'t, T: DeserializeOwned + 'static> FieldReader<'t> for Csv<T> {
+    type Future = LocalBoxFuture<'t, Result<Self, crate::Error>>;
+
+    fn read_field(req: &'t HttpRequest, mut field: Field, limits: &'t mut Limits) -> Self::Future {
+        async move {
+            let config = CsvConfig::from_req(req);
+            let field_name = field.name().to_owned();
+            let mut reader = RowReader::new(config);
+
+            while let Some(chunk) = field.try_next().await? {
+                limits.try_consume_limits(chunk.len(), true)?;
+                reader.push(&chunk).map_err(|e| crate::Error::Field {
+                    field_name: field_name.clone(),
+                    source: config.map_error(req, e),
+                })?;
+            }
+            reader.push(&[]).map_err(|e| crate::Error::Field {
+                field_name,
+                source: config.map_error(req, e),
+            })?;
+            Ok(Csv(reader.rows))
+        }
+        .boxed_local()
+    }
+}
+
+/// Incrementally parses CSV data into rows.
+struct RowReader<'a, T> {
+    config: &'a CsvConfig,
+    core: csv_core::Reader,
+    /// The data of the current record, and the end offset of each of its fields.
+    output: Vec<u8>,
+    output_len: usize,
+    ends: Vec<usize>,
+    ends_len: usize,
+    /// The line the current record started on.
+    line: u64,
+    headers: Option<ByteRecord>,
+    field_count: Option<usize>,
+    rows: Vec<T>,
+}
+
+impl<'a, T: DeserializeOwned> RowReader<'a, T> {
+    fn new(config: &'a CsvConfig) -> Self {
+        Self {
+            config,
+            core: csv_core::ReaderBuilder::new()
+                .delimiter(config.delimiter)
+                .build(),
+            output: vec![0; config.max_row_length.clamp(1, 1024)],
+            output_len: 0,
+            ends: vec![0; config.max_fields.clamp(1, 16)],
+            ends_len: 0,
+            line: 1,
+            headers: None,
+            field_count: None,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Parse a chunk of data, an empty chunk marks the end of the data.
+    fn push(&mut self, mut input: &[u8]) -> Result<(), CsvFieldError> {
+        let end = input.is_empty();
+        loop {
+            let (result, nin, nout, nend) = self.core.read_record(
+                input,
+                &mut self.output[self.output_len..],
+                &mut self.ends[self.ends_len..],
+            );
+            input = &input[nin..];
+            self.output_len += nout;
+            self.ends_len += nend;
+            match result {
+                ReadRecordResult::InputEmpty if !end => return Ok(()),
+                ReadRecordResult::InputEmpty => continue,
+                ReadRecordResult::OutputFull => {
+                    // The output must have space left for the parser to finish a record, so is
+                    // allowed to grow one byte beyond the limit
+                    let len = self.output.len();
+                    let max_len = self.config.max_row_length.saturating_add(1);
+                    if len >= max_len {
+                        return Err(self.row_too_long());
+                    }
+                    self.output.resize(len.saturating_mul(2).min(max_len), 0);
+                }
+                ReadRecordResult::OutputEndsFull => {
+                    // As with the output, allow one more field than the limit
+                    let len = self.ends.len();
+                    let max_len = self.config.max_fields.saturating_add(1);
+                    if len >= max_len {
+                        return Err(self.too_many_fields());
+                    }
+                    self.ends.resize(len.saturating_mul(2).min(max_len), 0);
+                }
+                ReadRecordResult::Record => {
+                    if self.output_len > self.config.max_row_length {
+                        return Err(self.row_too_long());
+                    }
+                    if self.ends_len > self.config.max_fields {
+                        return Err(self.too_many_fields());
+                    }
+                    self.record()?;
+                    self.output_len = 0;
+                    self.ends_len = 0;
+                    self.line = self.core.line();
+                }
+                ReadRecordResult::End => return Ok(()),
+            }
+        }
+    }
+
+    fn row_too_long(&self) -> CsvFieldError {
+        CsvFieldError::RowTooLong {
+            line: self.line,
+            limit: self.config.max_row_length,
+        }
+    }
+
+    fn too_many_fields(&self) -> CsvFieldError {
+        CsvFieldError::TooManyFields {
+            line: self.line,
+            limit: self.config.max_fields,
+        }
+    }
+
+    /// Handle a complete record.
+    fn record(&mut self) -> Result<(), CsvFieldError> {
+        let mut record = ByteRecord::with_capacity(self.output_len, self.ends_len);
+        let mut start = 0;
+        for &end in &self.ends[..self.ends_len] {
+            record.push_field(&self.output[start..end]);
+            start = end;
+        }
+
+        let expected = *self.field_count.get_or_insert(record.len());
+        if record.len() != expected {
+            return Err(CsvFieldError::Parse {
+                line: self.line,
+                column: expected.min(record.len()) as u64 + 1,
+                message: format!("found {} fields, expected {}", record.len(), expected),
+            });
+        }
+        if self.config.has_headers && self.headers.is_none() {
+            self.headers = Some(record);
+            return Ok(());
+        }
+
+        if self.rows.len() >= self.config.max_rows {
+            return Err(CsvFieldError::TooManyRows(self.config.max_rows));
+        }
+        let row = record.deserialize(self.headers.as_ref()).map_err(|e| {
+            let (column, message) = match e.kind() {
+                ::csv::ErrorKind::Deserialize { err, .. } => {
+                    (err.field().unwrap_or_default() + 1, err.kind().to_string())
+                }
+                _ => (1, e.to_string()),
+            };
+            CsvFieldError::Parse {
+                line: self.line,
+                column,
+                message,
+            }
+        })?;
+        self.rows.push(row);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum CsvFieldError {
+    /// A row could not be parsed or deserialized
+    #[display(fmt = "CSV error on line {} column {}: {}", line, column, message)]
+    Parse {
+        line: u64,
+        column: u64,
+        #[error(not(source))]
+        message: String,
+    },
+
+    /// The field has more than the maximum number of rows
+    #[display(fmt = "CSV has more than {} rows", _0)]
+    TooManyRows(#[error(not(source))] usize),
+
+    /// A row is longer than the maximum row length
+    #[display(fmt = "CSV row on line {} is longer than {} bytes", line, limit)]
+    RowTooLong { line: u64, limit: usize },
+
+    /// A row has more than the maximum number of fields
+    #[display(fmt = "CSV row on line {} has more than {} fields", line, limit)]
+    TooManyFields { line: u64, limit: usize },
+}
+
+impl ResponseError for CsvFieldError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// Configuration for the [`Csv`] field reader.
+#[derive(Clone)]
+pub struct CsvConfig {
+    err_handler: Option<Arc<dyn Fn(CsvFieldError, &HttpRequest) -> actix_web::Error + Send + Sync>>,
+    delimiter: u8,
+    has_headers: bool,
+    max_rows: usize,
+    max_row_length: usize,
+    max_fields: usize,
+}
+
+const DEFAULT_CONFIG: CsvConfig = CsvConfig {
+    err_handler: None,
+    delimiter: b',',
+    has_headers: true,
+    max_rows: 100_000,
+    max_row_length: 65_536, // 64 KiB
+    max_fields: 4_096,
+};
+
+impl CsvConfig {
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(CsvFieldError, &HttpRequest) -> actix_web::Error + Send + Sync + 'static,
+    {
+        self.err_handler = Some(Arc::new(f));
+        self
+    }
+
+    /// Extract payload config from app data. Check both `T` and `Data<T>`, in that order, and fall
+    /// back to the default payload config.
+    fn from_req(req: &HttpRequest) -> &Self {
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<web::Data<Self>>().map(|d| d.as_ref()))
+            .unwrap_or(&DEFAULT_CONFIG)
+    }
+
+    fn map_error(&self, req: &HttpRequest, err: CsvFieldError) -> actix_web::Error {
+        if let Some(err_handler) = self.err_handler.as_ref() {
+            (*err_handler)(err, req)
+        } else {
+            err.into()
+        }
+    }
+
+    /// Sets the field delimiter, by default `,`.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets whether the first row is a header row, by default true.
+    ///
+    /// When there is no header row the columns are mapped to the fields of `T` by position, so
+    /// `T` should be a tuple or a struct with its fields in the same order as the columns.
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    /// Sets the maximum number of rows, excluding the header row, by default 100,000.
+    pub fn max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = max_rows;
+        self
+    }
+
+    /// Sets the maximum length of a row in bytes, excluding quotes and delimiters, by default
+    /// 64 KiB.
+    pub fn max_row_length(mut self, max_row_length: usize) -> Self {
+        self.max_row_length = max_row_length;
+        self
+    }
+
+    /// Sets the maximum number of fields in a row, by default 4,096. Empty fields don't count
+    /// towards the row length, so this limits the memory used for a row of delimiters.
+    pub fn max_fields(mut self, max_fields: usize) -> Self {
+        self.max_fields = max_fields;
+        self
+    }
+}
+
+impl Default for CsvConfig {
+    fn default() -> Self {
+        DEFAULT_CONFIG
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::csv::{Csv, CsvConfig};
+    use crate::tests::send_form;
+    use crate::MultipartForm;
+    use actix_multipart_rfc7578::client::multipart;
+    use actix_web::http::StatusCode;
+    use actix_web::{web, App, HttpResponse, Responder};
+
+    #[derive(MultipartForm)]
+    struct TestCsv {
+        rows: Csv<(String, u32)>,
+    }
+
+    async fn test_csv_route(form: MultipartForm<TestCsv>) -> impl Responder {
+        assert_eq!(form.rows.len(), 2);
+        assert_eq!(form.rows[1], ("Smith, John".to_owned(), 42));
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_csv() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_csv_route))
+                .app_data(CsvConfig::default().max_rows(2))
+        });
+        let form = |csv: &str| {
+            let mut form = multipart::Form::default();
+            form.add_text("rows", csv.to_owned());
+            form
+        };
+
+        let csv = "name,age\nJane,30\n\"Smith, John\",42\n";
+        let response = send_form(&srv, form(csv), "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let csv = "name,age\nJane,30\nJohn,forty\n";
+        let mut response = send_form(&srv, form(csv), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.body().await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("line 3 column 2"));
+
+        let csv = "name,age\nA,1\nB,2\nC,3\n";
+        let response = send_form(&srv, form(csv), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // A row of delimiters has no content, but still has too many fields
+        let csv = format!("name,age\n{}\n", ",".repeat(5_000));
+        let mut response = send_form(&srv, form(&csv), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.body().await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("more than 4096 fields"));
+    }
+}
@@ -0,0 +1,465 @@
+//! Streaming decompression of parts with a `Content-Encoding` header, see
+//! [`MultipartFormConfig::decompress`](crate::MultipartFormConfig::decompress).
+use crate::Field;
+use actix_web::http::header;
+use actix_web::http::StatusCode;
+use actix_web::ResponseError;
+use derive_more::{Display, Error};
+
+/// The decompression options of a form, see
+/// [`MultipartFormConfig::decompress`](crate::MultipartFormConfig::decompress).
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Decompression {
+    pub(crate) enabled: bool,
+    pub(crate) by_file_name: bool,
+    // The limits are only read by the decoders, which depend on the enabled features
+    #[cfg_attr(
+        not(any(
+            feature = "compress-brotli",
+            feature = "compress-gzip",
+            feature = "compress-zstd"
+        )),
+        allow(dead_code)
+    )]
+    pub(crate) limit: usize,
+    #[cfg_attr(
+        not(any(
+            feature = "compress-brotli",
+            feature = "compress-gzip",
+            feature = "compress-zstd"
+        )),
+        allow(dead_code)
+    )]
+    pub(crate) max_ratio: usize,
+}
+
+/// A decompression error.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum DecompressError {
+    /// The part's `Content-Encoding` is not supported
+    #[display(fmt = "Unsupported content encoding: {}", _0)]
+    UnsupportedEncoding(#[error(not(source))] String),
+}
+
+impl ResponseError for DecompressError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    }
+}
+
+impl Decompression {
+    /// Decompress the field's data, if it has a `Content-Encoding` header (or a `.gz` filename).
+    ///
+    /// Exceeding the decompressed size limit or the maximum compression ratio fails with an
+    /// [`Error::LimitExceeded`](crate::Error::LimitExceeded), and invalid data fails with a
+    /// [`PayloadError::EncodingCorrupted`](actix_http::error::PayloadError::EncodingCorrupted).
+    pub(crate) fn decompress(&self, field: Field) -> Result<Field, crate::Error> {
+        if !self.enabled {
+            return Ok(field);
+        }
+        let encoding = match field.headers().get(&header::CONTENT_ENCODING) {
+            Some(encoding) => encoding
+                .to_str()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase(),
+            None if self.by_file_name
+                && field
+                    .file_name()
+                    .is_some_and(|name| name.to_ascii_lowercase().ends_with(".gz")) =>
+            {
+                "gzip".to_owned()
+            }
+            None => return Ok(field),
+        };
+        if encoding == "identity" {
+            return Ok(field);
+        }
+        #[cfg(any(
+            feature = "compress-brotli",
+            feature = "compress-gzip",
+            feature = "compress-zstd"
+        ))]
+        if let Some(decoder) = decoder::Decoder::new(&encoding) {
+            let options = *self;
+            let field_name = field.name().to_owned();
+            return Ok(field.decode_stream(|stream| {
+                decoder::Decompressor::new(stream, decoder, options, field_name)
+            }));
+        }
+        Err(crate::Error::Field {
+            field_name: field.name().to_owned(),
+            source: DecompressError::UnsupportedEncoding(encoding).into(),
+        })
+    }
+}
+
+#[cfg(any(
+    feature = "compress-brotli",
+    feature = "compress-gzip",
+    feature = "compress-zstd"
+))]
+mod decoder {
+    use super::Decompression;
+    use crate::{LimitKind, StreamLimitExceeded};
+    use actix_http::error::PayloadError;
+    use actix_multipart::MultipartError;
+    use bytes::Bytes;
+    use futures_core::stream::{LocalBoxStream, Stream};
+    use futures_util::StreamExt;
+    use std::io::{self, Write};
+    use std::pin::Pin;
+    use std::task::{ready, Context, Poll};
+
+    /// The compression ratio is only checked once this many bytes have been decompressed, as small
+    /// inputs can legitimately have a very high ratio.
+    const MIN_RATIO_CHECK: usize = 65_536; // 64 KiB
+
+    /// The size of the buffer used by the decoders.
+    #[cfg(any(feature = "compress-brotli", feature = "compress-gzip"))]
+    const BUFFER_SIZE: usize = 8_192; // 8 KiB
+
+    pub(super) enum Decoder {
+        #[cfg(feature = "compress-gzip")]
+        Gzip(flate2::write::GzDecoder<LimitedWriter>),
+        #[cfg(feature = "compress-gzip")]
+        Deflate(ZlibDecoder),
+        #[cfg(feature = "compress-brotli")]
+        Brotli(Box<brotli::DecompressorWriter<LimitedWriter>>),
+        #[cfg(feature = "compress-zstd")]
+        Zstd(zstd::stream::zio::Writer<LimitedWriter, zstd::stream::raw::Decoder<'static>>),
+    }
+
+    impl Decoder {
+        pub(super) fn new(encoding: &str) -> Option<Self> {
+            let writer = LimitedWriter::default();
+            Some(match encoding {
+                #[cfg(feature = "compress-gzip")]
+                "gzip" | "x-gzip" => Self::Gzip(flate2::write::GzDecoder::new(writer)),
+                #[cfg(feature = "compress-gzip")]
+                "deflate" => Self::Deflate(ZlibDecoder::new(writer)),
+                #[cfg(feature = "compress-brotli")]
+                "br" => Self::Brotli(Box::new(brotli::DecompressorWriter::new(
+                    writer,
+                    BUFFER_SIZE,
+                ))),
+                #[cfg(feature = "compress-zstd")]
+                "zstd" => Self::Zstd(zstd::stream::zio::Writer::new(
+                    writer,
+                    zstd::stream::raw::Decoder::new().ok()?,
+                )),
+                _ => return None,
+            })
+        }
+
+        /// Decompress a chunk, producing at most `limit` bytes of output.
+        fn write(&mut self, chunk: &[u8], limit: usize) -> io::Result<Bytes> {
+            self.writer().remaining = limit;
+            match self {
+                #[cfg(feature = "compress-gzip")]
+                Self::Gzip(d) => d.write_all(chunk).and_then(|_| d.flush()),
+                #[cfg(feature = "compress-gzip")]
+                Self::Deflate(d) => d.write(chunk),
+                #[cfg(feature = "compress-brotli")]
+                Self::Brotli(d) => d.write_all(chunk).and_then(|_| d.flush()),
+                #[cfg(feature = "compress-zstd")]
+                Self::Zstd(d) => d.write_all(chunk).and_then(|_| d.flush()),
+            }?;
+            Ok(self.writer().take())
+        }
+
+        /// Finish decompressing, checking that the compressed data was complete.
+        fn finish(&mut self, limit: usize) -> io::Result<Bytes> {
+            self.writer().remaining = limit;
+            match self {
+                #[cfg(feature = "compress-gzip")]
+                Self::Gzip(d) => d.try_finish(),
+                #[cfg(feature = "compress-gzip")]
+                Self::Deflate(d) => d.finish(),
+                #[cfg(feature = "compress-brotli")]
+                Self::Brotli(d) => d.close(),
+                #[cfg(feature = "compress-zstd")]
+                Self::Zstd(d) => d.finish(),
+            }?;
+            Ok(self.writer().take())
+        }
+
+        fn writer(&mut self) -> &mut LimitedWriter {
+            match self {
+                #[cfg(feature = "compress-gzip")]
+                Self::Gzip(d) => d.get_mut(),
+                #[cfg(feature = "compress-gzip")]
+                Self::Deflate(d) => &mut d.writer,
+                #[cfg(feature = "compress-brotli")]
+                Self::Brotli(d) => d.get_mut(),
+                #[cfg(feature = "compress-zstd")]
+                Self::Zstd(d) => d.writer_mut(),
+            }
+        }
+    }
+
+    /// A zlib decoder that checks that the stream is complete, which
+    /// [`flate2::write::ZlibDecoder`] doesn't.
+    #[cfg(feature = "compress-gzip")]
+    pub(super) struct ZlibDecoder {
+        decompress: flate2::Decompress,
+        writer: LimitedWriter,
+        done: bool,
+    }
+
+    #[cfg(feature = "compress-gzip")]
+    impl ZlibDecoder {
+        fn new(writer: LimitedWriter) -> Self {
+            Self {
+                decompress: flate2::Decompress::new(true),
+                writer,
+                done: false,
+            }
+        }
+
+        fn write(&mut self, mut input: &[u8]) -> io::Result<()> {
+            let mut buf = [0; BUFFER_SIZE];
+            loop {
+                if self.done {
+                    // Any data after the end of the stream is invalid
+                    return match input.is_empty() {
+                        true => Ok(()),
+                        false => Err(io::ErrorKind::InvalidData.into()),
+                    };
+                }
+                let total_in = self.decompress.total_in();
+                let total_out = self.decompress.total_out();
+                let status = self
+                    .decompress
+                    .decompress(input, &mut buf, flate2::FlushDecompress::None)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                let consumed = (self.decompress.total_in() - total_in) as usize;
+                let produced = (self.decompress.total_out() - total_out) as usize;
+                input = &input[consumed..];
+                self.writer.write_all(&buf[..produced])?;
+                self.done = status == flate2::Status::StreamEnd;
+                if !self.done && produced < buf.len() && (input.is_empty() || consumed == 0) {
+                    return match input.is_empty() {
+                        true => Ok(()),
+                        false => Err(io::ErrorKind::InvalidData.into()),
+                    };
+                }
+            }
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            self.write(&[])?;
+            match self.done {
+                true => Ok(()),
+                false => Err(io::ErrorKind::UnexpectedEof.into()),
+            }
+        }
+    }
+
+    /// Collects the decompressed data, failing once the limit is reached so that a decompression
+    /// bomb is never fully expanded into memory.
+    #[derive(Default)]
+    pub(super) struct LimitedWriter {
+        buf: Vec<u8>,
+        remaining: usize,
+        exceeded: bool,
+    }
+
+    impl LimitedWriter {
+        fn take(&mut self) -> Bytes {
+            Bytes::from(std::mem::take(&mut self.buf))
+        }
+    }
+
+    impl Write for LimitedWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if buf.len() > self.remaining {
+                self.exceeded = true;
+                return Err(io::ErrorKind::OutOfMemory.into());
+            }
+            self.remaining -= buf.len();
+            self.buf.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    pub(super) struct Decompressor {
+        stream: LocalBoxStream<'static, Result<Bytes, MultipartError>>,
+        /// The decoder, which is `None` once finished.
+        decoder: Option<Decoder>,
+        options: Decompression,
+        field_name: String,
+        compressed: usize,
+        decompressed: usize,
+    }
+
+    impl Decompressor {
+        pub(super) fn new(
+            stream: LocalBoxStream<'static, Result<Bytes, MultipartError>>,
+            decoder: Decoder,
+            options: Decompression,
+            field_name: String,
+        ) -> Self {
+            Self {
+                stream,
+                decoder: Some(decoder),
+                options,
+                field_name,
+                compressed: 0,
+                decompressed: 0,
+            }
+        }
+
+        /// The limit that applies to the data decompressed so far, and the number of bytes that
+        /// may be decompressed before exceeding it.
+        fn allowance(&self) -> (LimitKind, usize) {
+            let ratio_limit = self
+                .compressed
+                .saturating_mul(self.options.max_ratio)
+                .max(MIN_RATIO_CHECK);
+            let (kind, limit) = if self.options.limit <= ratio_limit {
+                (LimitKind::Decompressed, self.options.limit)
+            } else {
+                (LimitKind::CompressionRatio, ratio_limit)
+            };
+            (kind, limit.saturating_sub(self.decompressed))
+        }
+    }
+
+    impl Stream for Decompressor {
+        type Item = Result<Bytes, MultipartError>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let this = &mut *self;
+            loop {
+                let chunk = match &this.decoder {
+                    Some(_) => ready!(this.stream.poll_next_unpin(cx)),
+                    None => return Poll::Ready(None),
+                };
+                if let Some(Ok(chunk)) = &chunk {
+                    this.compressed += chunk.len();
+                }
+                let (kind, allowance) = this.allowance();
+                let decoder = this.decoder.as_mut().unwrap();
+                let (result, finished) = match chunk {
+                    Some(Ok(chunk)) => (decoder.write(&chunk, allowance), false),
+                    Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                    None => (decoder.finish(allowance), true),
+                };
+                let exceeded = decoder.writer().exceeded;
+                if finished || result.is_err() {
+                    this.decoder = None;
+                }
+                match result {
+                    Ok(decompressed) if decompressed.is_empty() => {}
+                    Ok(decompressed) => {
+                        this.decompressed += decompressed.len();
+                        return Poll::Ready(Some(Ok(decompressed)));
+                    }
+                    Err(_) if exceeded => {
+                        let limit = match kind {
+                            LimitKind::Decompressed => this.options.limit,
+                            _ => this.options.max_ratio,
+                        };
+                        let err = StreamLimitExceeded {
+                            kind,
+                            field_name: std::mem::take(&mut this.field_name),
+                            limit,
+                        };
+                        return Poll::Ready(Some(Err(err.into())));
+                    }
+                    Err(_) => {
+                        return Poll::Ready(Some(Err(PayloadError::EncodingCorrupted.into())));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "compress-gzip"))]
+mod tests {
+    use crate::bytes::Bytes;
+    use crate::{MultipartForm, MultipartFormConfig};
+    use actix_web::http::StatusCode;
+    use actix_web::{web, App, HttpResponse, Responder};
+    use awc::Client;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// Build a form with a single part with the given headers and data.
+    fn body(headers: &str, data: &[u8]) -> Vec<u8> {
+        let mut body = format!(
+            "--AaB03x\r\n\
+            Content-Disposition: form-data; name=\"data\"; filename=\"data.gz\"\r\n\
+            {headers}\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n--AaB03x--\r\n");
+        body
+    }
+
+    #[derive(MultipartForm)]
+    struct TestDecompress {
+        data: Bytes,
+    }
+
+    async fn test_decompress_route(form: MultipartForm<TestDecompress>) -> impl Responder {
+        assert_eq!(form.data.data, "Hello, world!");
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_decompress() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_decompress_route))
+                .app_data(
+                    MultipartFormConfig::default()
+                        .decompress(true)
+                        .decompress_gz_files(true),
+                )
+        });
+        let send = |body: Vec<u8>| {
+            Client::default()
+                .post(srv.url("/"))
+                .content_type("multipart/form-data; boundary=AaB03x")
+                .send_body(body)
+        };
+
+        let response = send(body("Content-Encoding: gzip\r\n", &gzip(b"Hello, world!")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Decompressed by the `.gz` filename
+        let response = send(body("", &gzip(b"Hello, world!"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Exceeds the maximum compression ratio
+        let response = send(body("", &gzip(&[0; 1_048_576]))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // Not valid gzip data
+        let response = send(body("", b"Hello, world!")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = send(body("Content-Encoding: compress\r\n", b"Hello, world!"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
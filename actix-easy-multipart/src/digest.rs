@@ -0,0 +1,528 @@
+//! Streaming computation and verification of content digests for the [`Bytes`](crate::bytes::Bytes)
+//! and [`Tempfile`](crate::tempfile::Tempfile) field readers, enabled using the `digest` feature.
+//!
+//! The digests are configured using [`DigestConfig`], and when verification is enabled the data is
+//! checked against any of the following supplied by the client:
+//!
+//! - A `Content-MD5` part header, see [RFC 1864](https://www.rfc-editor.org/rfc/rfc1864).
+//! - A `Digest` part header, see [RFC 3230](https://www.rfc-editor.org/rfc/rfc3230).
+//! - A `Repr-Digest` part header, see [RFC 9530](https://www.rfc-editor.org/rfc/rfc9530).
+//! - A sibling field sent before the file, see [`DigestConfig::digest_field`].
+//!
+//! The headers describe the data as it was sent, so they are not verified for a part whose data has
+//! been transfer decoded or decompressed (see
+//! [`MultipartFormConfig::decode_transfer_encoding`](crate::MultipartFormConfig::decode_transfer_encoding)
+//! and [`MultipartFormConfig::decompress`](crate::MultipartFormConfig::decompress)). The computed
+//! digests and a sibling digest field are always of the data as read by the field.
+//!
+//! ```
+//! # use actix_easy_multipart::digest::{Algorithm, DigestConfig};
+//! # use actix_web::App;
+//! let app = App::new().app_data(
+//!     DigestConfig::default()
+//!         .algorithm(Algorithm::Sha256)
+//!         .digest_field("_sha256", Algorithm::Sha256)
+//!         .verify(true),
+//! );
+//! ```
+use crate::field::Field;
+use actix_web::http::header::{HeaderMap, HeaderName};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, ResponseError};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use derive_more::{Display, Error};
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// A digest value is short, so a longer digest field is not buffered.
+const MAX_FIELD_LEN: usize = 256;
+
+/// A digest algorithm.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Algorithm {
+    #[display(fmt = "sha-256")]
+    Sha256,
+    #[display(fmt = "sha-1")]
+    Sha1,
+    #[display(fmt = "md5")]
+    Md5,
+    #[display(fmt = "blake3")]
+    Blake3,
+    #[display(fmt = "crc32c")]
+    Crc32c,
+}
+
+impl Algorithm {
+    /// Look up an algorithm by its name in a `Digest` or `Repr-Digest` header.
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "sha-256" => Self::Sha256,
+            "sha" | "sha-1" => Self::Sha1,
+            "md5" => Self::Md5,
+            "blake3" => Self::Blake3,
+            "crc32c" => Self::Crc32c,
+            _ => return None,
+        })
+    }
+}
+
+/// A set of algorithms.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Algorithms(u8);
+
+impl Algorithms {
+    pub(crate) const NONE: Algorithms = Algorithms(0);
+
+    pub(crate) fn insert(&mut self, algorithm: Algorithm) {
+        self.0 |= 1 << algorithm as u8;
+    }
+
+    fn contains(&self, algorithm: Algorithm) -> bool {
+        self.0 & (1 << algorithm as u8) != 0
+    }
+}
+
+/// The digests computed for a field, see [`DigestConfig`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Digests {
+    pub sha256: Option<[u8; 32]>,
+    pub sha1: Option<[u8; 20]>,
+    pub md5: Option<[u8; 16]>,
+    pub blake3: Option<[u8; 32]>,
+    pub crc32c: Option<u32>,
+}
+
+impl Digests {
+    /// Get the digest computed using the given algorithm, if any. A CRC32C checksum is given in
+    /// big-endian byte order.
+    pub fn get(&self, algorithm: Algorithm) -> Option<Vec<u8>> {
+        match algorithm {
+            Algorithm::Sha256 => self.sha256.map(|d| d.to_vec()),
+            Algorithm::Sha1 => self.sha1.map(|d| d.to_vec()),
+            Algorithm::Md5 => self.md5.map(|d| d.to_vec()),
+            Algorithm::Blake3 => self.blake3.map(|d| d.to_vec()),
+            Algorithm::Crc32c => self.crc32c.map(|d| d.to_be_bytes().to_vec()),
+        }
+    }
+
+    /// Get the digest computed using the given algorithm as a lowercase hex string, if any.
+    pub fn hex(&self, algorithm: Algorithm) -> Option<String> {
+        self.get(algorithm).map(hex::encode)
+    }
+}
+
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum IntegrityError {
+    /// The data does not match the digest supplied by the client
+    #[display(fmt = "The data does not match the {} digest", _0)]
+    Mismatch(#[error(not(source))] Algorithm),
+    /// A digest supplied by the client could not be parsed
+    #[display(fmt = "Invalid digest in {}", _0)]
+    InvalidDigest(#[error(not(source))] String),
+    /// A digest field was sent after the field it is for
+    #[display(fmt = "The digest of {} must be sent before it", _0)]
+    DigestAfterField(#[error(not(source))] String),
+}
+
+impl ResponseError for IntegrityError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// Configuration for the digests computed by the [`Bytes`](crate::bytes::Bytes) and
+/// [`Tempfile`](crate::tempfile::Tempfile) field readers.
+#[derive(Clone)]
+pub struct DigestConfig {
+    algorithms: Algorithms,
+    verify: bool,
+    digest_field: Option<(String, Algorithm)>,
+}
+
+const DEFAULT_CONFIG: DigestConfig = DigestConfig {
+    algorithms: Algorithms::NONE,
+    verify: false,
+    digest_field: None,
+};
+
+impl DigestConfig {
+    /// Extract digest config from app data. Check both `T` and `Data<T>`, in that order, and fall
+    /// back to the default digest config.
+    pub(crate) fn from_req(req: &HttpRequest) -> &Self {
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<web::Data<Self>>().map(|d| d.as_ref()))
+            .unwrap_or(&DEFAULT_CONFIG)
+    }
+
+    /// Add an algorithm to compute the digest of each field with. By default no digests are
+    /// computed.
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithms.insert(algorithm);
+        self
+    }
+
+    /// Set whether the data is verified against the digests supplied by the client, failing with
+    /// an [`IntegrityError`] if they don't match. The digest for any supported algorithm is
+    /// computed as needed, even if it was not added using [`algorithm`](Self::algorithm). By
+    /// default digests supplied by the client are ignored.
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Set a suffix for the names of fields that give the hex-encoded digest of another field,
+    /// e.g. with the suffix `_sha256` the field `file_sha256` gives the digest of the field
+    /// `file`. The digest field must be sent before the field it is for, otherwise the form fails
+    /// with [`IntegrityError::DigestAfterField`], and is otherwise read as usual. Only used when
+    /// [`verify`](Self::verify) is enabled.
+    pub fn digest_field(mut self, suffix: impl Into<String>, algorithm: Algorithm) -> Self {
+        self.digest_field = Some((suffix.into(), algorithm));
+        self
+    }
+}
+
+impl Default for DigestConfig {
+    fn default() -> Self {
+        DEFAULT_CONFIG
+    }
+}
+
+/// Record the value of a digest field, or attach a previously recorded value to the field that
+/// it is for. A field that has been read is recorded as `None`, so that a digest field sent after
+/// it can be rejected.
+pub(crate) async fn read_digest_field(
+    req: &HttpRequest,
+    field: Field,
+    digest_fields: &mut HashMap<String, Option<String>>,
+) -> Result<Field, crate::Error> {
+    let config = DigestConfig::from_req(req);
+    let (suffix, algorithm) = match &config.digest_field {
+        Some(digest_field) if config.verify => digest_field,
+        _ => return Ok(field),
+    };
+    match field.name().strip_suffix(suffix.as_str()) {
+        Some(name) if !name.is_empty() => {
+            if let Some(None) = digest_fields.get(name) {
+                return Err(crate::Error::Field {
+                    field_name: field.name().to_owned(),
+                    source: IntegrityError::DigestAfterField(name.to_owned()).into(),
+                });
+            }
+            let name = name.to_owned();
+            let (field, value) = field.read_short_value(MAX_FIELD_LEN).await?;
+            if let Some(value) = value {
+                digest_fields.insert(name, Some(value));
+            }
+            Ok(field)
+        }
+        _ => {
+            let digest = digest_fields
+                .insert(field.name().to_owned(), None)
+                .flatten()
+                .map(|digest| (*algorithm, digest));
+            Ok(field.with_expected_digest(digest))
+        }
+    }
+}
+
+/// Computes the digests of a field's data as it is read, and verifies them once complete.
+pub(crate) struct Hasher {
+    field_name: String,
+    sha256: Option<Sha256>,
+    sha1: Option<Sha1>,
+    md5: Option<Md5>,
+    blake3: Option<Box<blake3::Hasher>>,
+    crc32c: Option<u32>,
+    expected: Vec<(Algorithm, Vec<u8>)>,
+}
+
+impl Hasher {
+    /// Create a hasher for the field, computing the configured digests, any additional
+    /// algorithms, and the digests needed to verify the field.
+    pub(crate) fn new(
+        req: &HttpRequest,
+        field: &Field,
+        additional: Algorithms,
+    ) -> Result<Self, crate::Error> {
+        let config = DigestConfig::from_req(req);
+        let expected = if config.verify {
+            expected_digests(field).map_err(|err| crate::Error::Field {
+                field_name: field.name().to_owned(),
+                source: err.into(),
+            })?
+        } else {
+            Vec::new()
+        };
+        let mut algorithms = config.algorithms;
+        algorithms.0 |= additional.0;
+        for (algorithm, _) in &expected {
+            algorithms.insert(*algorithm);
+        }
+        let enabled = |algorithm| algorithms.contains(algorithm);
+        Ok(Hasher {
+            field_name: field.name().to_owned(),
+            sha256: enabled(Algorithm::Sha256).then(Sha256::new),
+            sha1: enabled(Algorithm::Sha1).then(Sha1::new),
+            md5: enabled(Algorithm::Md5).then(Md5::new),
+            blake3: enabled(Algorithm::Blake3).then(Default::default),
+            crc32c: enabled(Algorithm::Crc32c).then_some(0),
+            expected,
+        })
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        if let Some(sha256) = &mut self.sha256 {
+            sha256.update(data);
+        }
+        if let Some(sha1) = &mut self.sha1 {
+            sha1.update(data);
+        }
+        if let Some(md5) = &mut self.md5 {
+            md5.update(data);
+        }
+        if let Some(blake3) = &mut self.blake3 {
+            blake3.update(data);
+        }
+        if let Some(crc32c) = &mut self.crc32c {
+            *crc32c = crc32c::crc32c_append(*crc32c, data);
+        }
+    }
+
+    /// Get the digests, checking that they match those supplied by the client.
+    pub(crate) fn finish(self) -> Result<Digests, crate::Error> {
+        let digests = Digests {
+            sha256: self.sha256.map(|d| d.finalize().into()),
+            sha1: self.sha1.map(|d| d.finalize().into()),
+            md5: self.md5.map(|d| d.finalize().into()),
+            blake3: self.blake3.map(|d| *d.finalize().as_bytes()),
+            crc32c: self.crc32c,
+        };
+        for (algorithm, expected) in &self.expected {
+            if digests.get(*algorithm).as_ref() != Some(expected) {
+                return Err(crate::Error::Field {
+                    field_name: self.field_name,
+                    source: IntegrityError::Mismatch(*algorithm).into(),
+                });
+            }
+        }
+        Ok(digests)
+    }
+}
+
+/// Get the digests supplied by the client for the field, ignoring unsupported algorithms. The
+/// headers are ignored if the data has been decoded, as they describe the data that was sent.
+fn expected_digests(field: &Field) -> Result<Vec<(Algorithm, Vec<u8>)>, IntegrityError> {
+    let mut expected = Vec::new();
+    if !field.is_decoded() {
+        expected_header_digests(field.headers(), &mut expected)?;
+    }
+    if let Some((algorithm, digest)) = field.expected_digest() {
+        let digest = hex::decode(digest.trim())
+            .map_err(|_| IntegrityError::InvalidDigest(format!("{} field", field.name())))?;
+        expected.push((*algorithm, digest));
+    }
+    Ok(expected)
+}
+
+/// Get the digests given by the `Content-MD5`, `Digest` and `Repr-Digest` headers.
+fn expected_header_digests(
+    headers: &HeaderMap,
+    expected: &mut Vec<(Algorithm, Vec<u8>)>,
+) -> Result<(), IntegrityError> {
+    let content_md5 = HeaderName::from_static("content-md5");
+    if let Some(md5) = header_str(headers, &content_md5)? {
+        let md5 = BASE64
+            .decode(md5.trim())
+            .map_err(|_| IntegrityError::InvalidDigest(content_md5.to_string()))?;
+        expected.push((Algorithm::Md5, md5));
+    }
+    for name in ["digest", "repr-digest"] {
+        let name = HeaderName::from_static(name);
+        if let Some(value) = header_str(headers, &name)? {
+            expected.extend(
+                parse_digest_header(value)
+                    .ok_or(IntegrityError::InvalidDigest(name.to_string()))?,
+            );
+        }
+    }
+    Ok(())
+}
+
+fn header_str<'a>(
+    headers: &'a HeaderMap,
+    name: &HeaderName,
+) -> Result<Option<&'a str>, IntegrityError> {
+    headers
+        .get(name)
+        .map(|value| {
+            value
+                .to_str()
+                .map_err(|_| IntegrityError::InvalidDigest(name.to_string()))
+        })
+        .transpose()
+}
+
+/// Parse a `Digest` header (e.g. `SHA-256=X48E9q...`) or a `Repr-Digest` header (e.g.
+/// `sha-256=:X48E9q...:`), both of which are a list of base64 encoded digests.
+fn parse_digest_header(value: &str) -> Option<Vec<(Algorithm, Vec<u8>)>> {
+    let mut digests = Vec::new();
+    for item in value.split(',') {
+        let (name, digest) = item.split_once('=')?;
+        let algorithm = match Algorithm::from_name(name.trim()) {
+            Some(algorithm) => algorithm,
+            None => continue,
+        };
+        let digest = digest.trim();
+        let digest = digest
+            .strip_prefix(':')
+            .and_then(|d| d.strip_suffix(':'))
+            .unwrap_or(digest);
+        digests.push((algorithm, BASE64.decode(digest).ok()?));
+    }
+    Some(digests)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bytes::Bytes;
+    use crate::digest::{parse_digest_header, Algorithm, DigestConfig};
+    use crate::text::Text;
+    use crate::{MultipartForm, MultipartFormConfig};
+    use actix_web::http::StatusCode;
+    use actix_web::{web, App, HttpResponse, Responder};
+    use awc::Client;
+
+    #[test]
+    fn test_parse_digest_header() {
+        let digests = parse_digest_header(
+            "sha-256=:47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=:, unknown=:AA==:",
+        )
+        .unwrap();
+        assert_eq!(digests.len(), 1);
+        assert_eq!(digests[0].0, Algorithm::Sha256);
+        let digests = parse_digest_header("SHA=2jmj7l5rSw0yVb/vlWAYkK/YBwk=").unwrap();
+        assert_eq!(digests[0].0, Algorithm::Sha1);
+        assert!(parse_digest_header("md5=:not base64:").is_none());
+    }
+
+    #[derive(MultipartForm)]
+    struct TestDigest {
+        #[multipart(rename = "file_sha256")]
+        _file_sha256: Option<Text<String>>,
+        file: Bytes,
+    }
+
+    async fn test_digest_route(form: MultipartForm<TestDigest>) -> impl Responder {
+        assert_eq!(
+            form.file.digests.hex(Algorithm::Sha256).unwrap(),
+            "315f5bdb76d078c43b8ac0064e4a0164612b1fce77c869345bfc94c75894edd3"
+        );
+        assert_eq!(form.file.digests.crc32c, Some(0xc8a106e5));
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_digest() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_digest_route))
+                .app_data(
+                    DigestConfig::default()
+                        .algorithm(Algorithm::Sha256)
+                        .algorithm(Algorithm::Crc32c)
+                        .digest_field("_sha256", Algorithm::Sha256)
+                        .verify(true),
+                )
+                .app_data(MultipartFormConfig::default().decode_transfer_encoding(true))
+        });
+        let send = |headers: &str, sha256: &str| {
+            let body = format!(
+                "--AaB03x\r\n\
+                Content-Disposition: form-data; name=\"file_sha256\"\r\n\
+                \r\n\
+                {sha256}\r\n\
+                --AaB03x\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+                {headers}\r\n\
+                Hello, world!\r\n\
+                --AaB03x--\r\n"
+            );
+            Client::default()
+                .post(srv.url("/"))
+                .content_type("multipart/form-data; boundary=AaB03x")
+                .send_body(body)
+        };
+        let sha256 = "315f5bdb76d078c43b8ac0064e4a0164612b1fce77c869345bfc94c75894edd3";
+
+        let response = send("Content-MD5: bNNVbesNpUvKBgtMOUeYOQ==\r\n", sha256)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send("Repr-Digest: md5=:bNNVbesNpUvKBgtMOUeYOQ==:\r\n", sha256)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The sibling field doesn't match
+        let response = send("", &sha256.replace('3', "4")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // The header doesn't match
+        let response = send("Digest: MD5=AAAAAAAAAAAAAAAAAAAAAA==\r\n", sha256)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // The headers are not verified against decoded data, but the sibling field is
+        let send_encoded = |sha256: &str| {
+            let body = format!(
+                "--AaB03x\r\n\
+                Content-Disposition: form-data; name=\"file_sha256\"\r\n\
+                \r\n\
+                {sha256}\r\n\
+                --AaB03x\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+                Content-Transfer-Encoding: base64\r\n\
+                Content-MD5: bNNVbesNpUvKBgtMOUeYOQ==\r\n\
+                \r\n\
+                SGVsbG8sIHdvcmxkIQ==\r\n\
+                --AaB03x--\r\n"
+            );
+            Client::default()
+                .post(srv.url("/"))
+                .content_type("multipart/form-data; boundary=AaB03x")
+                .send_body(body)
+        };
+        let response = send_encoded(sha256).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send_encoded(&sha256.replace('3', "4")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // The sibling field is sent after the file
+        let body = format!(
+            "--AaB03x\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+            \r\n\
+            Hello, world!\r\n\
+            --AaB03x\r\n\
+            Content-Disposition: form-data; name=\"file_sha256\"\r\n\
+            \r\n\
+            {sha256}\r\n\
+            --AaB03x--\r\n"
+        );
+        let response = Client::default()
+            .post(srv.url("/"))
+            .content_type("multipart/form-data; boundary=AaB03x")
+            .send_body(body)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
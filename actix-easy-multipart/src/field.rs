@@ -0,0 +1,296 @@
+//! A single part of a multipart form.
+use crate::file_name;
+use crate::parser::Part;
+use crate::text::TextOptions;
+use actix_multipart::MultipartError;
+use actix_web::http::header::{
+    self, ContentDisposition, DispositionParam, DispositionType, HeaderMap,
+};
+use bytes::Bytes;
+use futures_core::stream::{LocalBoxStream, Stream};
+use futures_util::{stream, StreamExt};
+use mime::Mime;
+use std::future::ready;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// A single field in a multipart form, which is a stream of the field's data.
+pub struct Field {
+    name: String,
+    headers: HeaderMap,
+    content_type: Option<Mime>,
+    content_disposition: ContentDisposition,
+    urlencoded: bool,
+    form_charset: Option<String>,
+    detected_content_type: Option<Mime>,
+    text_options: Option<TextOptions>,
+    #[cfg(feature = "digest")]
+    expected_digest: Option<(crate::digest::Algorithm, String)>,
+    #[cfg(feature = "digest")]
+    decoded: bool,
+    stream: LocalBoxStream<'static, Result<Bytes, MultipartError>>,
+}
+
+impl Field {
+    pub(crate) fn new<S>(
+        name: String,
+        headers: HeaderMap,
+        content_disposition: ContentDisposition,
+        stream: S,
+    ) -> Self
+    where
+        S: Stream<Item = Result<Bytes, MultipartError>> + 'static,
+    {
+        // An unparseable content type is treated as an arbitrary binary
+        let content_type = headers.get(&header::CONTENT_TYPE).map(|ct| {
+            ct.to_str()
+                .ok()
+                .and_then(|ct| ct.parse().ok())
+                .unwrap_or(mime::APPLICATION_OCTET_STREAM)
+        });
+        Self {
+            name,
+            headers,
+            content_type,
+            content_disposition,
+            urlencoded: false,
+            form_charset: None,
+            detected_content_type: None,
+            text_options: None,
+            #[cfg(feature = "digest")]
+            expected_digest: None,
+            #[cfg(feature = "digest")]
+            decoded: false,
+            stream: stream.boxed_local(),
+        }
+    }
+
+    /// Create a field from a name/value pair of an `application/x-www-form-urlencoded` body.
+    pub(crate) fn from_urlencoded(name: String, value: Bytes) -> Self {
+        let content_disposition = ContentDisposition {
+            disposition: DispositionType::FormData,
+            parameters: vec![DispositionParam::Name(name.clone())],
+        };
+        Self {
+            urlencoded: true,
+            ..Self::new(
+                name,
+                HeaderMap::new(),
+                content_disposition,
+                stream::once(ready(Ok(value))),
+            )
+        }
+    }
+
+    /// Create a field from a part of a `multipart/form-data` body.
+    ///
+    /// According to [RFC 7578 §4.2](https://datatracker.ietf.org/doc/html/rfc7578#section-4.2) a
+    /// Content-Disposition header must always be present and set to "form-data", with a `name`
+    /// parameter.
+    pub(crate) fn from_form_data(part: Part) -> Result<Self, MultipartError> {
+        let content_disposition = part_content_disposition(&part.headers)
+            .filter(|cd| cd.disposition == DispositionType::FormData)
+            .ok_or(MultipartError::NoContentDisposition)?;
+        let name = content_disposition
+            .get_name()
+            .ok_or(MultipartError::NoContentDisposition)?
+            .to_owned();
+        Ok(Self::new(
+            name,
+            part.headers,
+            content_disposition,
+            part.body,
+        ))
+    }
+
+    /// Create a field with the given name from a part where the Content-Disposition header is
+    /// optional, such as a part nested within a `multipart/mixed` field (see
+    /// [RFC 2388 §5.2](https://www.rfc-editor.org/rfc/rfc2388#section-5.2)) or a part of a
+    /// `multipart/related` body.
+    pub(crate) fn from_part(name: String, part: Part) -> Self {
+        let content_disposition =
+            part_content_disposition(&part.headers).unwrap_or(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![],
+            });
+        Self::new(name, part.headers, content_disposition, part.body)
+    }
+
+    /// Returns the field's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns a reference to the field's header map.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Returns the field's content (mime) type, if a `Content-Type` header was present.
+    pub fn content_type(&self) -> Option<&Mime> {
+        self.content_type.as_ref()
+    }
+
+    /// Returns the field's Content-Disposition.
+    pub fn content_disposition(&self) -> &ContentDisposition {
+        &self.content_disposition
+    }
+
+    /// Returns the field's filename, see [`file_name::from_content_disposition`].
+    pub fn file_name(&self) -> Option<String> {
+        file_name::from_content_disposition(&self.content_disposition)
+    }
+
+    /// Whether the field is a name/value pair of an `application/x-www-form-urlencoded` body,
+    /// rather than a part of a multipart body.
+    pub fn is_urlencoded(&self) -> bool {
+        self.urlencoded
+    }
+
+    /// Returns the default charset for text in the form, as given by a preceding `_charset_`
+    /// field (see [RFC 7578 §4.6](https://www.rfc-editor.org/rfc/rfc7578#section-4.6)).
+    pub fn form_charset(&self) -> Option<&str> {
+        self.form_charset.as_deref()
+    }
+
+    pub(crate) fn with_form_charset(mut self, form_charset: Option<String>) -> Self {
+        self.form_charset = form_charset;
+        self
+    }
+
+    /// Returns the content type detected from the field's first bytes, if content type sniffing is
+    /// enabled and the format was recognised, see the [`sniff`](crate::sniff) module.
+    pub fn detected_content_type(&self) -> Option<&Mime> {
+        self.detected_content_type.as_ref()
+    }
+
+    pub(crate) fn with_detected_content_type(mut self, content_type: Option<Mime>) -> Self {
+        self.detected_content_type = content_type;
+        self
+    }
+
+    /// Returns the text options set using the field's `#[multipart(...)]` attributes, see
+    /// [`TextOptions`].
+    pub fn text_options(&self) -> Option<&TextOptions> {
+        self.text_options.as_ref()
+    }
+
+    pub(crate) fn with_text_options(mut self, text_options: Option<TextOptions>) -> Self {
+        self.text_options = text_options;
+        self
+    }
+
+    /// Returns the hex-encoded digest given by a preceding digest field, see
+    /// [`DigestConfig::digest_field`](crate::digest::DigestConfig::digest_field).
+    #[cfg(feature = "digest")]
+    pub(crate) fn expected_digest(&self) -> Option<&(crate::digest::Algorithm, String)> {
+        self.expected_digest.as_ref()
+    }
+
+    #[cfg(feature = "digest")]
+    pub(crate) fn with_expected_digest(
+        mut self,
+        expected_digest: Option<(crate::digest::Algorithm, String)>,
+    ) -> Self {
+        self.expected_digest = expected_digest;
+        self
+    }
+
+    /// Whether the field's data has been transfer decoded or decompressed, so that it is not the
+    /// data that was sent.
+    #[cfg(feature = "digest")]
+    pub(crate) fn is_decoded(&self) -> bool {
+        self.decoded
+    }
+
+    /// Read at least the first `len` bytes of the field (or all of it, if shorter), returning a
+    /// field that will yield the same data.
+    pub(crate) async fn peek(mut self, len: usize) -> Result<(Self, Bytes), MultipartError> {
+        let mut chunks = Vec::new();
+        let mut read = 0;
+        while read < len {
+            match self.stream.next().await {
+                Some(chunk) => {
+                    let chunk = chunk?;
+                    read += chunk.len();
+                    chunks.push(chunk);
+                }
+                None => break,
+            }
+        }
+
+        let head = Bytes::from(chunks.concat());
+        let chunk = (!head.is_empty()).then(|| Ok(head.clone()));
+        let field = self.map_stream(|stream| stream::iter(chunk).chain(stream));
+        Ok((field, head))
+    }
+
+    /// Read the value of a short field (e.g. `_charset_`), returning a field that will yield the
+    /// same data. The value is trimmed, and is `None` if empty or longer than `max_len` bytes.
+    pub(crate) async fn read_short_value(
+        self,
+        max_len: usize,
+    ) -> Result<(Self, Option<String>), MultipartError> {
+        let (field, head) = self.peek(max_len + 1).await?;
+        let value = if head.len() <= max_len {
+            std::str::from_utf8(&head)
+                .ok()
+                .map(|value| value.trim().to_owned())
+                .filter(|value| !value.is_empty())
+        } else {
+            None
+        };
+        Ok((field, value))
+    }
+
+    /// Replace the field's data stream, e.g. to decode it.
+    pub(crate) fn map_stream<F, S>(mut self, f: F) -> Self
+    where
+        F: FnOnce(LocalBoxStream<'static, Result<Bytes, MultipartError>>) -> S,
+        S: Stream<Item = Result<Bytes, MultipartError>> + 'static,
+    {
+        self.stream = f(self.stream).boxed_local();
+        self
+    }
+
+    /// Replace the field's data stream with a decoder of the data that was sent.
+    pub(crate) fn decode_stream<F, S>(self, f: F) -> Self
+    where
+        F: FnOnce(LocalBoxStream<'static, Result<Bytes, MultipartError>>) -> S,
+        S: Stream<Item = Result<Bytes, MultipartError>> + 'static,
+    {
+        Self {
+            #[cfg(feature = "digest")]
+            decoded: true,
+            ..self.map_stream(f)
+        }
+    }
+
+    /// Returns the boundary of a field with a nested multipart body, usually `multipart/mixed`, see
+    /// [RFC 2388 §5.2](https://www.rfc-editor.org/rfc/rfc2388#section-5.2). Any other `multipart/*`
+    /// subtype is treated in the same way.
+    pub(crate) fn nested_boundary(&self) -> Result<Option<String>, MultipartError> {
+        match &self.content_type {
+            Some(mime) if mime.type_() == mime::MULTIPART => mime
+                .get_param(mime::BOUNDARY)
+                .map(|boundary| Some(boundary.as_str().to_owned()))
+                .ok_or(MultipartError::Boundary),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Parse the Content-Disposition header of a part.
+pub(crate) fn part_content_disposition(headers: &HeaderMap) -> Option<ContentDisposition> {
+    headers
+        .get(&header::CONTENT_DISPOSITION)
+        .and_then(|cd| ContentDisposition::from_raw(cd).ok())
+}
+
+impl Stream for Field {
+    type Item = Result<Bytes, MultipartError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.stream.poll_next_unpin(cx)
+    }
+}
@@ -0,0 +1,126 @@
+//! Decoding and sanitization of the filenames of uploaded files.
+use actix_web::http::header::ContentDisposition;
+use encoding_rs::Encoding;
+use unicode_normalization::UnicodeNormalization;
+
+/// The maximum length in bytes of a sanitized filename, which is the limit of most filesystems.
+pub const MAX_LEN: usize = 255;
+
+/// Names that are reserved for devices on Windows, with or without an extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Get the filename from a Content-Disposition, preferring the `filename*` extended parameter
+/// (see [RFC 5987](https://www.rfc-editor.org/rfc/rfc5987)) over the `filename` parameter.
+///
+/// Note the filename is untrusted user input, see [`sanitize`].
+pub fn from_content_disposition(cd: &ContentDisposition) -> Option<String> {
+    cd.get_filename_ext()
+        .and_then(|ext| {
+            Encoding::for_label(ext.charset.to_string().as_bytes())
+                .and_then(|encoding| {
+                    encoding.decode_without_bom_handling_and_without_replacement(&ext.value)
+                })
+                .map(|name| name.into_owned())
+        })
+        .or_else(|| cd.get_filename().map(str::to_owned))
+}
+
+/// Sanitize a filename so that it is safe to use on disk.
+///
+/// - Any directory components are removed.
+/// - The name is normalized to Unicode NFC.
+/// - Control characters, and characters that are reserved on Windows, are replaced with `_`.
+/// - Leading dots, and trailing dots and spaces, are removed.
+/// - Names reserved for devices on Windows (e.g. `CON`) are prefixed with `_`.
+/// - The name is truncated to [`MAX_LEN`] bytes, keeping the extension if possible.
+///
+/// Returns `None` if nothing remains of the name.
+pub fn sanitize(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name = name
+        .nfc()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+    let mut name = name
+        .trim_start_matches('.')
+        .trim_end_matches(['.', ' '])
+        .to_owned();
+    if name.is_empty() {
+        return None;
+    }
+
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+    {
+        name.insert(0, '_');
+    }
+
+    if name.len() > MAX_LEN {
+        let extension = name
+            .rfind('.')
+            .map(|i| name[i..].to_owned())
+            .filter(|extension| extension.len() <= MAX_LEN / 2)
+            .unwrap_or_default();
+        let mut end = MAX_LEN - extension.len();
+        while !name.is_char_boundary(end) {
+            end -= 1;
+        }
+        name.truncate(end);
+        name.push_str(&extension);
+    }
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file_name::{from_content_disposition, sanitize, MAX_LEN};
+    use actix_web::http::header::{ContentDisposition, HeaderValue};
+
+    #[test]
+    fn test_from_content_disposition() {
+        let cd = |value: &'static str| {
+            ContentDisposition::from_raw(&HeaderValue::from_static(value)).unwrap()
+        };
+        assert_eq!(
+            from_content_disposition(&cd(
+                "form-data; name=\"f\"; filename=\"a.txt\"; filename*=UTF-8''%E2%82%AC%20rates.txt"
+            )),
+            Some("€ rates.txt".to_owned())
+        );
+        assert_eq!(
+            from_content_disposition(&cd("form-data; name=\"f\"; filename*=iso-8859-1''caf%E9")),
+            Some("café".to_owned())
+        );
+        assert_eq!(
+            from_content_disposition(&cd("form-data; name=\"f\"; filename=\"a.txt\"")),
+            Some("a.txt".to_owned())
+        );
+        assert_eq!(from_content_disposition(&cd("form-data; name=\"f\"")), None);
+    }
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("../../etc/passwd").unwrap(), "passwd");
+        assert_eq!(sanitize("C:\\Users\\a\\file.txt").unwrap(), "file.txt");
+        assert_eq!(sanitize("a\u{0}b<c>.txt").unwrap(), "a_b_c_.txt");
+        assert_eq!(sanitize("..hidden. . ").unwrap(), "hidden");
+        assert_eq!(sanitize("cafe\u{301}").unwrap(), "caf\u{e9}");
+        assert_eq!(sanitize("con.txt").unwrap(), "_con.txt");
+        assert_eq!(sanitize("..").as_deref(), None);
+        assert_eq!(sanitize("dir/").as_deref(), None);
+
+        let long = format!("{}.txt", "é".repeat(200));
+        let sanitized = sanitize(&long).unwrap();
+        assert!(sanitized.len() <= MAX_LEN);
+        assert!(sanitized.ends_with("é.txt"));
+    }
+}
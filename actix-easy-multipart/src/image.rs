@@ -0,0 +1,395 @@
+//! Reads a field as an image, enabled using the `image` feature.
+use crate::bytes::Bytes;
+use crate::{file_name, Field, FieldReader, Limits};
+use ::image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, ResponseError};
+use derive_more::{Display, Error};
+use futures_core::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use mime::Mime;
+use std::io::Cursor;
+use std::sync::Arc;
+
+/// Read the field as an image.
+///
+/// The image is fully decoded to check that it is valid, but only the encoded data is kept. The
+/// dimensions and the size of the decoded image are checked before decoding, see
+/// [`ImageConfig::max_dimensions`] and [`ImageConfig::decoded_limit`]. The decoded image is only
+/// held while the field is read, but must also fit within the form's remaining memory limit, see
+/// [`Limits::check_memory`].
+///
+/// The PNG, JPEG, GIF and WebP formats are supported, see [`ImageConfig::formats`].
+#[derive(Debug)]
+pub struct Image {
+    /// The encoded image data, which has been re-encoded if [`ImageConfig::strip_metadata`] is
+    /// enabled.
+    pub data: bytes::Bytes,
+    /// The format of the image, detected from the data.
+    pub format: ImageFormat,
+    /// The width of the image in pixels.
+    pub width: u32,
+    /// The height of the image in pixels.
+    pub height: u32,
+    /// The `filename*` or `filename` value in the `content-disposition` header.
+    pub file_name: Option<String>,
+}
+
+impl Image {
+    /// The content type of the image's format.
+    pub fn content_type(&self) -> Mime {
+        self.format
+            .to_mime_type()
+            .parse()
+            .unwrap_or(mime::APPLICATION_OCTET_STREAM)
+    }
+
+    /// The filename, sanitized so that it is safe to use on disk, see [`file_name::sanitize`].
+    pub fn sanitized_file_name(&self) -> Option<String> {
+        self.file_name.as_deref().and_then(file_name::sanitize)
+    }
+}
+
+impl<'t> FieldReader<'t> for Image {
+    type Future = LocalBoxFuture<'t, Result<Self, crate::Error>>;
+
+    fn read_field(req: &'t HttpRequest, mut field: Field, limits: &'t mut Limits) -> Self::Future {
+        async move {
+            let config = ImageConfig::from_req(req);
+            let field_name = field.name().to_owned();
+            let field_error = |err: ImageFieldError| crate::Error::Field {
+                field_name: field_name.clone(),
+                source: config.map_error(req, err),
+            };
+
+            let bytes = Bytes::read_unscanned(req, &mut field, limits).await?;
+
+            // Only the header is read, so the image can be checked before decoding the pixels
+            let (format, decoded_size) = read_header(&bytes.data, config).map_err(field_error)?;
+            limits.check_memory(decoded_size)?;
+
+            // Decoding is CPU intensive, so is run on the blocking thread pool
+            let data = bytes.data.clone();
+            let decoded_limit = config.decoded_limit;
+            let strip_metadata = config.strip_metadata;
+            let (width, height, decoded) =
+                web::block(move || decode(data, format, decoded_limit, strip_metadata))
+                    .await
+                    .map_err(|e| crate::Error::Field {
+                        field_name: field_name.clone(),
+                        source: e.into(),
+                    })?
+                    .map_err(field_error)?;
+
+            Ok(Image {
+                data: decoded.map_or(bytes.data, Into::into),
+                format,
+                width,
+                height,
+                file_name: bytes.file_name,
+            })
+        }
+        .boxed_local()
+    }
+}
+
+/// Detect the format of the image and check it against the config, without decoding the pixels.
+/// Returns the format and the size of the decoded image.
+fn read_header(
+    data: &bytes::Bytes,
+    config: &ImageConfig,
+) -> Result<(ImageFormat, usize), ImageFieldError> {
+    let reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .map_err(|e| ImageFieldError::Decode(e.into()))?;
+    let format = reader
+        .format()
+        .ok_or(ImageFieldError::UnsupportedFormat(None))?;
+    let allowed = match &config.formats {
+        Some(formats) => formats.contains(&format),
+        None => DEFAULT_FORMATS.contains(&format),
+    };
+    if !allowed || !format.reading_enabled() {
+        return Err(ImageFieldError::UnsupportedFormat(Some(format)));
+    }
+    let decoder = reader.into_decoder().map_err(ImageFieldError::Decode)?;
+    let (width, height) = decoder.dimensions();
+    if width > config.max_width || height > config.max_height {
+        return Err(ImageFieldError::TooLarge { width, height });
+    }
+    match usize::try_from(decoder.total_bytes()) {
+        Ok(size) if size <= config.decoded_limit => Ok((format, size)),
+        _ => Err(ImageFieldError::TooLarge { width, height }),
+    }
+}
+
+/// Decode the image, and re-encode it without its metadata if `strip_metadata` is set, returning
+/// the dimensions and any new data.
+fn decode(
+    data: bytes::Bytes,
+    format: ImageFormat,
+    decoded_limit: usize,
+    strip_metadata: bool,
+) -> Result<(u32, u32, Option<Vec<u8>>), ImageFieldError> {
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    reader.no_limits();
+    let mut decoder = reader.into_decoder().map_err(ImageFieldError::Decode)?;
+    let (width, height) = decoder.dimensions();
+    let mut image_limits = ::image::Limits::default();
+    image_limits.max_alloc = Some(decoded_limit as u64);
+    decoder
+        .set_limits(image_limits)
+        .map_err(ImageFieldError::Decode)?;
+
+    let orientation = decoder.orientation().map_err(ImageFieldError::Decode)?;
+    let mut image = DynamicImage::from_decoder(decoder).map_err(ImageFieldError::Decode)?;
+    if !strip_metadata {
+        return Ok((width, height, None));
+    }
+    // The orientation is stored in the EXIF metadata, so must be applied to the pixels
+    image.apply_orientation(orientation);
+    let mut data = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut data), format)
+        .map_err(ImageFieldError::Encode)?;
+    Ok((image.width(), image.height(), Some(data)))
+}
+
+/// The formats that are allowed by default.
+const DEFAULT_FORMATS: [ImageFormat; 4] = [
+    ImageFormat::Png,
+    ImageFormat::Jpeg,
+    ImageFormat::Gif,
+    ImageFormat::WebP,
+];
+
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum ImageFieldError {
+    /// The image format could not be detected, or is not allowed
+    #[display(fmt = "Unsupported image format")]
+    UnsupportedFormat(#[error(not(source))] Option<ImageFormat>),
+
+    /// The image is larger than the maximum dimensions, or its decoded size is larger than the
+    /// decoded limit
+    #[display(fmt = "Image dimensions {}x{} are too large", width, height)]
+    TooLarge { width: u32, height: u32 },
+
+    /// The image could not be decoded
+    #[display(fmt = "Image decode error: {}", _0)]
+    Decode(::image::ImageError),
+
+    /// The image could not be re-encoded
+    #[display(fmt = "Image encode error: {}", _0)]
+    Encode(::image::ImageError),
+}
+
+impl ResponseError for ImageFieldError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ImageFieldError::UnsupportedFormat(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ImageFieldError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            ImageFieldError::Decode(_) => StatusCode::BAD_REQUEST,
+            ImageFieldError::Encode(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// Configuration for the [`Image`] field reader.
+#[derive(Clone)]
+pub struct ImageConfig {
+    err_handler:
+        Option<Arc<dyn Fn(ImageFieldError, &HttpRequest) -> actix_web::Error + Send + Sync>>,
+    formats: Option<Vec<ImageFormat>>,
+    max_width: u32,
+    max_height: u32,
+    decoded_limit: usize,
+    strip_metadata: bool,
+}
+
+const DEFAULT_CONFIG: ImageConfig = ImageConfig {
+    err_handler: None,
+    formats: None,
+    max_width: 10_000,
+    max_height: 10_000,
+    decoded_limit: 67_108_864, // 64 MiB
+    strip_metadata: false,
+};
+
+impl ImageConfig {
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(ImageFieldError, &HttpRequest) -> actix_web::Error + Send + Sync + 'static,
+    {
+        self.err_handler = Some(Arc::new(f));
+        self
+    }
+
+    /// Extract payload config from app data. Check both `T` and `Data<T>`, in that order, and fall
+    /// back to the default payload config.
+    fn from_req(req: &HttpRequest) -> &Self {
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<web::Data<Self>>().map(|d| d.as_ref()))
+            .unwrap_or(&DEFAULT_CONFIG)
+    }
+
+    fn map_error(&self, req: &HttpRequest, err: ImageFieldError) -> actix_web::Error {
+        if let Some(err_handler) = self.err_handler.as_ref() {
+            (*err_handler)(err, req)
+        } else {
+            err.into()
+        }
+    }
+
+    /// Sets the allowed image formats, by default PNG, JPEG, GIF and WebP.
+    pub fn formats(mut self, formats: Vec<ImageFormat>) -> Self {
+        self.formats = Some(formats);
+        self
+    }
+
+    /// Sets the maximum width and height of an image in pixels, which are checked before the image
+    /// is decoded, by default 10,000x10,000. The [decoded limit](Self::decoded_limit) also
+    /// applies, which is reached first by large images with the default limits.
+    pub fn max_dimensions(mut self, max_width: u32, max_height: u32) -> Self {
+        self.max_width = max_width;
+        self.max_height = max_height;
+        self
+    }
+
+    /// Sets the maximum size in bytes of the decoded image, which is checked before the image is
+    /// decoded, by default 64MiB (e.g. a 4096x4096 RGBA image). A larger image fails with
+    /// [`ImageFieldError::TooLarge`].
+    ///
+    /// The decoded image is only held while the field is read, but must also fit within the
+    /// form's remaining memory limit, otherwise it fails with
+    /// [`LimitKind::Memory`](crate::LimitKind::Memory).
+    pub fn decoded_limit(mut self, decoded_limit: usize) -> Self {
+        self.decoded_limit = decoded_limit;
+        self
+    }
+
+    /// Sets whether to re-encode images to remove their metadata (such as EXIF data, which may
+    /// include the location a photo was taken), by default false.
+    ///
+    /// The EXIF orientation is applied to the re-encoded image. Animated GIF and WebP images are
+    /// re-encoded as a still image of their first frame, without an error, so don't enable this
+    /// if animations must be kept.
+    pub fn strip_metadata(mut self, strip_metadata: bool) -> Self {
+        self.strip_metadata = strip_metadata;
+        self
+    }
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        DEFAULT_CONFIG
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::image::{Image, ImageConfig};
+    use crate::{MultipartForm, MultipartFormConfig};
+    use ::image::{ImageFormat, RgbImage};
+    use actix_web::http::StatusCode;
+    use actix_web::{web, App, HttpResponse, Responder};
+    use awc::Client;
+    use std::io::Cursor;
+
+    fn encode(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+        let mut data = Vec::new();
+        RgbImage::new(width, height)
+            .write_to(&mut Cursor::new(&mut data), format)
+            .unwrap();
+        data
+    }
+
+    fn body(data: &[u8]) -> Vec<u8> {
+        let mut body = b"--AaB03x\r\n\
+            Content-Disposition: form-data; name=\"image\"; filename=\"a\"\r\n\
+            \r\n"
+            .to_vec();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n--AaB03x--\r\n");
+        body
+    }
+
+    #[derive(MultipartForm)]
+    struct TestImage {
+        image: Image,
+    }
+
+    async fn test_image_route(form: MultipartForm<TestImage>) -> impl Responder {
+        assert_eq!(form.image.format, ImageFormat::Png);
+        assert_eq!((form.image.width, form.image.height), (20, 10));
+        assert_eq!(form.image.content_type(), mime::IMAGE_PNG);
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_image() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_image_route))
+                .app_data(
+                    ImageConfig::default()
+                        .formats(vec![ImageFormat::Png])
+                        .max_dimensions(100, 100)
+                        .decoded_limit(10_000)
+                        .strip_metadata(true),
+                )
+        });
+        let send = |body: Vec<u8>| {
+            Client::default()
+                .post(srv.url("/"))
+                .content_type("multipart/form-data; boundary=AaB03x")
+                .send_body(body)
+        };
+
+        let response = send(body(&encode(20, 10, ImageFormat::Png))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(body(&encode(200, 10, ImageFormat::Png)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let response = send(body(&encode(20, 10, ImageFormat::Gif))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        // 60x60 RGB is within the dimensions, but decodes to more than 10,000 bytes
+        let response = send(body(&encode(60, 60, ImageFormat::Png))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let mut truncated = encode(20, 10, ImageFormat::Png);
+        truncated.truncate(truncated.len() - 20);
+        let response = send(body(&truncated)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    async fn test_image_memory_route(_: MultipartForm<TestImage>) -> impl Responder {
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_image_memory_limit() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_image_memory_route))
+                .app_data(MultipartFormConfig::default().memory_limit(5_000))
+        });
+        let send = |body: Vec<u8>| {
+            Client::default()
+                .post(srv.url("/"))
+                .content_type("multipart/form-data; boundary=AaB03x")
+                .send_body(body)
+        };
+
+        let response = send(body(&encode(20, 10, ImageFormat::Png))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The encoded image is small, but 60x60 RGB decodes to more than the memory limit
+        let response = send(body(&encode(60, 60, ImageFormat::Png))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
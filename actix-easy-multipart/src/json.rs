@@ -1,16 +1,21 @@
 //! Deserializes a field as JSON.
 use crate::bytes::Bytes;
-use crate::{field_mime, FieldReader, Limits};
-use actix_multipart::Field;
+use crate::{is_format, is_valid_content_type, Field, FieldReader, Limits};
 use actix_web::http::StatusCode;
 use actix_web::{web, HttpRequest, ResponseError};
 use derive_more::{Deref, DerefMut, Display, Error};
 use futures_core::future::LocalBoxFuture;
-use futures_util::FutureExt;
+use futures_util::{FutureExt, TryStreamExt};
+use mime::Mime;
 use serde::de::DeserializeOwned;
+use serde_path_to_error::Track;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 /// Deserialize from JSON.
+///
+/// The nesting depth of the data is limited by [`JsonConfig::max_depth`], and when deserializing
+/// fails the error includes the path to the value that failed, such as `metadata.tags[3]`.
 #[derive(Debug, Deref, DerefMut)]
 pub struct Json<T: DeserializeOwned>(pub T);
 
@@ -23,67 +28,264 @@ impl<T: DeserializeOwned> Json<T> {
 impl<'t, T: DeserializeOwned + 'static> FieldReader<'t> for Json<T> {
     type Future = LocalBoxFuture<'t, Result<Self, crate::Error>>;
 
-    fn read_field(req: &'t HttpRequest, field: Field, limits: &'t mut Limits) -> Self::Future {
+    fn read_field(req: &'t HttpRequest, mut field: Field, limits: &'t mut Limits) -> Self::Future {
         async move {
             let config = JsonConfig::from_req(req);
             let field_name = field.name().to_owned();
 
-            if config.validate_content_type {
-                let valid = if let Some(mime) = field_mime(&field) {
-                    mime.subtype() == mime::JSON || mime.suffix() == Some(mime::JSON)
-                } else {
-                    false
-                };
-                if !valid {
-                    return Err(crate::Error::Field {
-                        field_name,
-                        source: config.map_error(req, JsonFieldError::ContentType),
-                    });
+            if !is_valid_content_type(&field, config.validate_content_type, is_json) {
+                return Err(crate::Error::Field {
+                    field_name,
+                    source: config.map_error(req, JsonFieldError::ContentType),
+                });
+            }
+
+            let bytes = Bytes::read_unscanned(req, &mut field, limits).await?;
+
+            let value = config
+                .check(&bytes.data)
+                .and_then(|_| from_slice(&bytes.data).map_err(JsonFieldError::Deserialize));
+            Ok(Json(value.map_err(|e| crate::Error::Field {
+                field_name,
+                source: config.map_error(req, e),
+            })?))
+        }
+        .boxed_local()
+    }
+}
+
+/// Deserialize the data, keeping track of the path to the value that failed.
+fn from_slice<T: DeserializeOwned>(data: &[u8]) -> Result<T, PathError> {
+    let mut deserializer = serde_json::Deserializer::from_slice(data);
+    let value = serde_path_to_error::deserialize(&mut deserializer)?;
+    // Trailing data is not part of any value, so has an empty path
+    deserializer
+        .end()
+        .map_err(|e| PathError::new(Track::new().path(), e))?;
+    Ok(value)
+}
+
+/// Check the nesting depth of JSON data, and optionally that no object has a duplicate key.
+///
+/// This doesn't validate the data, any syntax errors are left to be reported by the deserializer.
+fn check_structure(
+    data: &[u8],
+    max_depth: usize,
+    reject_duplicate_keys: bool,
+) -> Result<(), JsonFieldError> {
+    enum Frame {
+        Array,
+        Object {
+            keys: HashSet<String>,
+            expect_key: bool,
+        },
+    }
+    let mut stack = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        match data[i] {
+            b'"' => {
+                let start = i;
+                i += 1;
+                while i < data.len() && data[i] != b'"' {
+                    i += if data[i] == b'\\' { 2 } else { 1 };
+                }
+                if i >= data.len() {
+                    return Ok(());
                 }
+                if let Some(Frame::Object { keys, expect_key }) = stack.last_mut() {
+                    if *expect_key && reject_duplicate_keys {
+                        let raw = &data[start..=i];
+                        let key = if raw.contains(&b'\\') {
+                            match serde_json::from_slice::<String>(raw) {
+                                Ok(key) => key,
+                                Err(_) => return Ok(()),
+                            }
+                        } else {
+                            String::from_utf8_lossy(&raw[1..raw.len() - 1]).into_owned()
+                        };
+                        if keys.contains(&key) {
+                            return Err(JsonFieldError::DuplicateKey(key));
+                        }
+                        keys.insert(key);
+                    }
+                    *expect_key = false;
+                }
+            }
+            open @ (b'{' | b'[') => {
+                if stack.len() >= max_depth {
+                    return Err(JsonFieldError::TooDeep(max_depth));
+                }
+                stack.push(match open {
+                    b'{' => Frame::Object {
+                        keys: HashSet::new(),
+                        expect_key: true,
+                    },
+                    _ => Frame::Array,
+                });
+            }
+            b'}' | b']' => {
+                stack.pop();
             }
+            b',' => {
+                if let Some(Frame::Object { expect_key, .. }) = stack.last_mut() {
+                    *expect_key = true;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
+fn is_json(mime: &Mime) -> bool {
+    is_format(mime, &["json"], "json")
+}
 
-            let bytes = Bytes::read_field(req, field, limits).await?;
+/// Deserialize each line of a field as JSON, known as
+/// [JSON Lines](https://jsonlines.org/) or newline delimited JSON.
+///
+/// Unlike [`Json`] the lines are deserialized as the data arrives, so only the current line is
+/// buffered rather than the whole field. The field's data still counts towards the form's memory
+/// limit, as the deserialized items are kept. Blank lines are skipped. The number of lines and
+/// their length are limited by [`JsonConfig::max_items`] and [`JsonConfig::max_line_length`].
+#[derive(Debug, Deref, DerefMut)]
+pub struct JsonLines<T: DeserializeOwned>(pub Vec<T>);
 
-            Ok(Json(serde_json::from_slice(bytes.data.as_ref()).map_err(
-                |e| crate::Error::Field {
-                    field_name,
-                    source: config.map_error(req, JsonFieldError::Deserialize(e)),
-                },
-            )?))
+impl<T: DeserializeOwned> JsonLines<T> {
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<'t, T: DeserializeOwned + 'static> FieldReader<'t> for JsonLines<T> {
+    type Future = LocalBoxFuture<'t, Result<Self, crate::Error>>;
+
+    fn read_field(req: &'t HttpRequest, mut field: Field, limits: &'t mut Limits) -> Self::Future {
+        async move {
+            let config = JsonConfig::from_req(req);
+            let field_name = field.name().to_owned();
+            let field_error = |err: JsonFieldError| crate::Error::Field {
+                field_name: field_name.clone(),
+                source: config.map_error(req, err),
+            };
+
+            if !is_valid_content_type(&field, config.validate_content_type, is_json_lines) {
+                return Err(field_error(JsonFieldError::ContentType));
+            }
+
+            let mut items = Vec::new();
+            let mut line = Vec::new();
+            let mut line_number = 1;
+            let mut handle_line = |line: &[u8], line_number: u64| {
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    return Ok(());
+                }
+                if items.len() >= config.max_items {
+                    return Err(JsonFieldError::TooManyItems(config.max_items));
+                }
+                config.check(line)?;
+                let item = from_slice(line).map_err(|e| JsonFieldError::Line(line_number, e))?;
+                items.push(item);
+                Ok(())
+            };
+
+            while let Some(chunk) = field.try_next().await? {
+                limits.try_consume_limits(chunk.len(), true)?;
+                let mut rest = chunk.as_ref();
+                while let Some(end) = memchr::memchr(b'\n', rest) {
+                    let data = if line.is_empty() {
+                        &rest[..end]
+                    } else {
+                        line.extend_from_slice(&rest[..end]);
+                        &line
+                    };
+                    if data.len() > config.max_line_length {
+                        return Err(field_error(JsonFieldError::LineTooLong(line_number)));
+                    }
+                    handle_line(data, line_number).map_err(field_error)?;
+                    line.clear();
+                    line_number += 1;
+                    rest = &rest[end + 1..];
+                }
+                if line.len() + rest.len() > config.max_line_length {
+                    return Err(field_error(JsonFieldError::LineTooLong(line_number)));
+                }
+                line.extend_from_slice(rest);
+            }
+            handle_line(&line, line_number).map_err(field_error)?;
+            Ok(JsonLines(items))
         }
         .boxed_local()
     }
 }
 
+fn is_json_lines(mime: &Mime) -> bool {
+    is_json(mime)
+        || ["x-ndjson", "ndjson", "jsonl", "x-jsonlines"].contains(&mime.subtype().as_str())
+}
+
 #[derive(Debug, Display, Error)]
 #[non_exhaustive]
 pub enum JsonFieldError {
-    /// Deserialize error
+    /// Deserialize error, including the path to the value that failed such as `items[3].name`
     #[display(fmt = "Json deserialize error: {}", _0)]
-    Deserialize(serde_json::Error),
+    Deserialize(PathError),
 
     /// Content type error
     #[display(fmt = "Content type error")]
     ContentType,
+
+    /// A line of a [`JsonLines`] field could not be deserialized
+    #[display(fmt = "Json deserialize error on line {}: {}", _0, _1)]
+    Line(u64, PathError),
+
+    /// A [`JsonLines`] field has more than the maximum number of items
+    #[display(fmt = "Json lines has more than {} items", _0)]
+    TooManyItems(#[error(not(source))] usize),
+
+    /// A line of a [`JsonLines`] field is longer than the maximum line length
+    #[display(fmt = "Json line {} is too long", _0)]
+    LineTooLong(#[error(not(source))] u64),
+
+    /// Objects and arrays are nested more deeply than the maximum depth
+    #[display(fmt = "Json is nested more than {} levels deep", _0)]
+    TooDeep(#[error(not(source))] usize),
+
+    /// An object has a duplicate key, see [`JsonConfig::reject_duplicate_keys`]
+    #[display(fmt = "Json object has a duplicate key: {}", _0)]
+    DuplicateKey(#[error(not(source))] String),
 }
 
+/// A [`serde_json::Error`] along with the path to the value that failed.
+pub type PathError = serde_path_to_error::Error<serde_json::Error>;
+
 impl ResponseError for JsonFieldError {
     fn status_code(&self) -> StatusCode {
         StatusCode::BAD_REQUEST
     }
 }
 
-/// Configuration for the [`Json`] field reader.
+/// Configuration for the [`Json`] and [`JsonLines`] field readers.
 #[derive(Clone)]
 pub struct JsonConfig {
     err_handler:
         Option<Arc<dyn Fn(JsonFieldError, &HttpRequest) -> actix_web::Error + Send + Sync>>,
     validate_content_type: bool,
+    max_items: usize,
+    max_line_length: usize,
+    max_depth: usize,
+    reject_duplicate_keys: bool,
 }
 
 const DEFAULT_CONFIG: JsonConfig = JsonConfig {
     err_handler: None,
     validate_content_type: true,
+    max_items: 100_000,
+    max_line_length: 1_048_576, // 1 MiB
+    max_depth: 64,
+    reject_duplicate_keys: false,
 };
 
 impl JsonConfig {
@@ -111,11 +313,44 @@ impl JsonConfig {
         }
     }
 
+    /// Checks the structure of the data against the limits.
+    fn check(&self, data: &[u8]) -> Result<(), JsonFieldError> {
+        check_structure(data, self.max_depth, self.reject_duplicate_keys)
+    }
+
     /// Sets whether or not the field must have a valid `Content-Type` header to be parsed.
+    ///
+    /// A [`JsonLines`] field also accepts `application/x-ndjson` and `application/jsonl`.
     pub fn validate_content_type(mut self, validate_content_type: bool) -> Self {
         self.validate_content_type = validate_content_type;
         self
     }
+
+    /// Sets the maximum number of items in a [`JsonLines`] field, by default 100,000.
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = max_items;
+        self
+    }
+
+    /// Sets the maximum length of a line of a [`JsonLines`] field in bytes, by default 1 MiB.
+    pub fn max_line_length(mut self, max_line_length: usize) -> Self {
+        self.max_line_length = max_line_length;
+        self
+    }
+
+    /// Sets the maximum depth that objects and arrays may be nested, by default 64. Note that
+    /// [`serde_json`] has its own fixed limit of 127.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets whether an object with a duplicate key is rejected, by default false. Otherwise a
+    /// struct rejects a duplicate field, but other types such as maps keep the last value.
+    pub fn reject_duplicate_keys(mut self, reject_duplicate_keys: bool) -> Self {
+        self.reject_duplicate_keys = reject_duplicate_keys;
+        self
+    }
 }
 
 impl Default for JsonConfig {
@@ -126,7 +361,7 @@ impl Default for JsonConfig {
 
 #[cfg(test)]
 mod tests {
-    use crate::json::{Json, JsonConfig};
+    use crate::json::{Json, JsonConfig, JsonLines};
     use crate::tests::send_form;
     use crate::MultipartForm;
     use actix_multipart_rfc7578::client::multipart;
@@ -184,4 +419,85 @@ mod tests {
         let response = send_form(&srv, form, "/").await;
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    #[derive(MultipartForm)]
+    struct JsonLinesForm {
+        events: JsonLines<HashMap<String, i32>>,
+    }
+
+    async fn test_json_lines_route(form: MultipartForm<JsonLinesForm>) -> impl Responder {
+        assert_eq!(form.events.len(), 2);
+        assert_eq!(form.events[1]["b"], 2);
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_json_lines() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_json_lines_route))
+                .app_data(JsonConfig::default().max_items(2))
+        });
+        let form = |data: &'static str| {
+            let mut form = multipart::Form::default();
+            let mime = "application/x-ndjson".parse().unwrap();
+            form.add_reader_file_with_mime("events", Cursor::new(data), "", mime);
+            form
+        };
+
+        let response = send_form(&srv, form("{\"a\": 1}\n\n{\"b\": 2}\n"), "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let mut response = send_form(&srv, form("{\"a\": 1}\n{\"b\": }\n"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.body().await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("line 2"));
+
+        let response = send_form(&srv, form("{}\n{}\n{}\n"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[derive(MultipartForm)]
+    struct NestedForm {
+        json: Json<HashMap<String, Vec<u32>>>,
+    }
+
+    async fn test_nested_route(form: MultipartForm<NestedForm>) -> impl Responder {
+        assert_eq!(form.json["tags"], vec![1, 2]);
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_hardening() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_nested_route))
+                .app_data(
+                    JsonConfig::default()
+                        .validate_content_type(false)
+                        .max_depth(2)
+                        .reject_duplicate_keys(true),
+                )
+        });
+        let form = |json: &'static str| {
+            let mut form = multipart::Form::default();
+            form.add_text("json", json);
+            form
+        };
+
+        let response = send_form(&srv, form("{\"tags\": [1, 2]}"), "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The error includes the path to the value
+        let mut response = send_form(&srv, form("{\"tags\": [1, \"x\"]}"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.body().await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("tags[1]: invalid type"));
+
+        let response = send_form(&srv, form("{\"tags\": [[1]]}"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = send_form(&srv, form("{\"tags\": [], \"tags\": [1, 2]}"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
 }
@@ -1,22 +1,53 @@
 //! Typed multipart form extractor for actix-web.
 #![allow(clippy::type_complexity)]
+#[cfg(feature = "archive")]
+pub mod archive;
 pub mod bytes;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+#[cfg(feature = "csv")]
+pub mod csv;
+pub mod decompress;
+#[cfg(feature = "digest")]
+pub mod digest;
+mod field;
+pub mod file_name;
+#[cfg(feature = "image")]
+pub mod image;
 pub mod json;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+mod parser;
+pub mod problem;
+#[cfg(feature = "prost")]
+pub mod protobuf;
+pub mod related;
+pub mod scan;
+pub mod sniff;
+#[cfg_attr(not(any(feature = "tracing", feature = "metrics")), doc(hidden))]
+pub mod telemetry;
 #[cfg(feature = "tempfile")]
 pub mod tempfile;
 pub mod text;
-
-use actix_http::error::PayloadError;
-use actix_multipart::{Field, Multipart, MultipartError};
+#[cfg(feature = "toml")]
+pub mod toml;
+mod transfer_encoding;
+mod urlencoded;
+#[cfg(feature = "xml")]
+pub mod xml;
+#[cfg(feature = "yaml")]
+pub mod yaml;
+
+use actix_http::error::{ParseError, PayloadError};
+use actix_multipart::MultipartError;
 use actix_web::dev::Payload;
-use actix_web::http::{header, StatusCode};
-use actix_web::{web, FromRequest, HttpRequest, ResponseError};
+use actix_web::http::StatusCode;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest, ResponseError};
 use derive_more::{Deref, DerefMut, Display, Error, From};
 use futures_core::future::LocalBoxFuture;
-use futures_util::TryFutureExt;
 use futures_util::{FutureExt, TryStreamExt};
 use std::any::Any;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::future::{ready, Future};
 use std::sync::Arc;
 
@@ -24,10 +55,12 @@ use std::sync::Arc;
 #[cfg(test)]
 extern crate self as actix_easy_multipart;
 
-// Re-export actix-multipart for use in macro
+// Re-export actix-multipart, as its error type is used in `Error::Multipart`
 #[doc(hidden)]
 pub use actix_multipart;
 
+pub use field::Field;
+
 /// Implements the [`MultipartFormTrait`] for a struct so that it can be used with the
 /// [`struct@MultipartForm`] extractor.
 ///
@@ -54,7 +87,12 @@ pub use actix_multipart;
 /// You can also use `Vec<T>` and `Option<T>` provided that `T: FieldReader`.
 ///
 /// A [`Vec`] field corresponds to an upload with multiple parts under the
-/// [same field name](https://www.rfc-editor.org/rfc/rfc7578#section-4.3).
+/// [same field name](https://www.rfc-editor.org/rfc/rfc7578#section-4.3). Multiple files may also
+/// be sent as a single part with a nested `multipart/mixed` body, as described in
+/// [RFC 2388](https://www.rfc-editor.org/rfc/rfc2388#section-5.2), in which case each nested part
+/// is read as a separate field of the same name (and is subject to the same limits). Any other
+/// nested `multipart/*` body is read in the same way, but a nested part may not itself contain a
+/// multipart body.
 ///
 /// ```
 /// # #[cfg(feature = "tempfile")] {
@@ -137,11 +175,90 @@ pub use actix_multipart;
 /// #[multipart(duplicate_action = "deny")]
 /// struct Form { }
 /// ```
+///
+/// ## Error Accumulation
+///
+/// By default the form fails on the first field that cannot be processed. You can use the
+/// `#[multipart(accumulate_errors)]` attribute to instead continue processing the remaining
+/// fields, and return all of the field errors (including any missing fields) together in an
+/// [Error::Multiple] error. Errors from the multipart stream itself, and exceeded limits, still
+/// end processing immediately.
+///
+/// ```
+/// # use actix_easy_multipart::text::Text;
+/// # use actix_easy_multipart::MultipartForm;
+/// #[derive(MultipartForm)]
+/// #[multipart(accumulate_errors)]
+/// struct Form {
+///     name: Text<String>,
+///     age: Text<u32>,
+/// }
+/// ```
+///
+/// ## Matching Parts
+///
+/// When used with the [`MultipartMixed`](related::MultipartMixed) and
+/// [`MultipartRelated`](related::MultipartRelated) extractors, parts can be matched to fields by
+/// their `Content-ID` or position, or as the root part, see the [`related`] module.
+///
+/// ```
+/// # use actix_easy_multipart::bytes::Bytes;
+/// # use actix_easy_multipart::text::Text;
+/// # use actix_easy_multipart::MultipartForm;
+/// #[derive(MultipartForm)]
+/// struct Form {
+///     #[multipart(root)]
+///     envelope: Text<String>,
+///     #[multipart(content_id = "<attachment@example.com>")]
+///     attachment: Bytes,
+///     #[multipart(position = 2)]
+///     third: Option<Bytes>,
+/// }
+/// ```
+///
+/// ## Content Types
+///
+/// You can use the `#[multipart(content_types = "")]` attribute to restrict a field to a
+/// comma-separated list of content types, which are checked against the type detected from the
+/// data, see the [`sniff`] module.
+///
+/// ```
+/// # use actix_easy_multipart::bytes::Bytes;
+/// # use actix_easy_multipart::MultipartForm;
+/// #[derive(MultipartForm)]
+/// struct Form {
+///     #[multipart(content_types = "image/*, application/pdf")]
+///     scan: Bytes,
+/// }
+/// ```
+///
+/// ## Text Options
+///
+/// The text normalisation options of the [`TextConfig`](text::TextConfig) can be overridden for a
+/// single field, using the `trim`, `normalize_newlines`, `normalize_unicode = "nfc"` (or `"nfkc"`),
+/// `reject_control_chars`, `max_chars = 100` and `empty_as_none` attributes. These apply to the
+/// [`Text`](text::Text), [`TextFromStr`](text::TextFromStr) and [`Delimited`](text::Delimited)
+/// field readers.
+///
+/// ```
+/// # use actix_easy_multipart::text::Text;
+/// # use actix_easy_multipart::MultipartForm;
+/// #[derive(MultipartForm)]
+/// struct Form {
+///     #[multipart(trim, max_chars = 100)]
+///     name: Text<String>,
+///     #[multipart(trim, empty_as_none, normalize_newlines)]
+///     comment: Option<Text<String>>,
+///     #[multipart(trim = false)]
+///     password: Text<String>,
+/// }
+/// ```
 pub use actix_easy_multipart_derive::MultipartForm;
 
 #[derive(Debug, Display, Error, From)]
 pub enum Error {
     #[display(fmt = "{}", _0)]
+    #[from(ignore)]
     Multipart(actix_multipart::MultipartError),
 
     /// An error from a field handler in a form
@@ -165,17 +282,157 @@ pub enum Error {
     #[display(fmt = "Unsupported field `{}`", _0)]
     #[from(ignore)]
     UnsupportedField(#[error(not(source))] String),
+
+    /// A form limit was exceeded
+    #[display(
+        fmt = "The {} limit of {} was exceeded{}",
+        kind,
+        limit,
+        "field_name.as_ref().map(|n| format!(\" by field `{n}`\")).unwrap_or_default()"
+    )]
+    #[from(ignore)]
+    LimitExceeded {
+        /// The limit that was exceeded.
+        kind: LimitKind,
+        /// The name of the field being processed when the limit was exceeded.
+        field_name: Option<String>,
+        /// The configured value of the limit.
+        limit: usize,
+    },
+
+    /// Multiple errors, when the form uses `#[multipart(accumulate_errors)]`
+    #[display(
+        fmt = "{}",
+        "_0.iter().map(ToString::to_string).collect::<Vec<_>>().join(\"; \")"
+    )]
+    #[from(ignore)]
+    Multiple(#[error(not(source))] Vec<Error>),
+}
+
+impl From<MultipartError> for Error {
+    fn from(err: MultipartError) -> Self {
+        if let MultipartError::Payload(PayloadError::Io(io_err)) = &err {
+            if let Some(exceeded) = io_err
+                .get_ref()
+                .and_then(|err| err.downcast_ref::<StreamLimitExceeded>())
+            {
+                return Error::LimitExceeded {
+                    kind: exceeded.kind,
+                    field_name: Some(exceeded.field_name.clone()),
+                    limit: exceeded.limit,
+                };
+            }
+        }
+        Error::Multipart(err)
+    }
+}
+
+/// A limit exceeded while decoding a field's data stream, which can only fail with a
+/// [`MultipartError`]. It is converted back into an [`Error::LimitExceeded`] when the field reader
+/// returns the error.
+#[derive(Debug, Display, Error)]
+#[display(fmt = "The {} limit of {} was exceeded", kind, limit)]
+pub(crate) struct StreamLimitExceeded {
+    pub(crate) kind: LimitKind,
+    pub(crate) field_name: String,
+    pub(crate) limit: usize,
+}
+
+impl From<StreamLimitExceeded> for MultipartError {
+    fn from(err: StreamLimitExceeded) -> Self {
+        PayloadError::Io(std::io::Error::other(err)).into()
+    }
+}
+
+/// Returned by a built-in field reader to treat a field as if it was not sent, such as an empty
+/// text field with the `empty_as_none` option. The field group readers convert it into an absent
+/// value, or into an [`Error::MissingField`] for a required field.
+#[derive(Debug, Display, Error)]
+#[display(fmt = "Field was not sent")]
+pub(crate) struct NotSent;
+
+// Reported in the same way as the `Error::MissingField` that it stands for
+impl ResponseError for NotSent {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+impl NotSent {
+    pub(crate) fn error(field_name: String) -> Error {
+        Error::Field {
+            field_name,
+            source: NotSent.into(),
+        }
+    }
+}
+
+impl Error {
+    /// Whether processing of the form can continue after this error when accumulating errors.
+    fn is_accumulable(&self) -> bool {
+        !matches!(self, Error::Multipart(_) | Error::LimitExceeded { .. })
+    }
+
+    /// Whether a field reader returned the error to treat the field as if it was not sent.
+    fn is_not_sent(&self) -> bool {
+        matches!(self, Error::Field { source, .. } if source.as_error::<NotSent>().is_some())
+    }
 }
 
 impl ResponseError for Error {
     fn status_code(&self) -> StatusCode {
         match &self {
             Error::Field { source, .. } => source.as_response_error().status_code(),
+            Error::LimitExceeded { .. }
+            | Error::Multipart(MultipartError::Payload(PayloadError::Overflow))
+            | Error::Multipart(MultipartError::Parse(ParseError::TooLarge)) => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
             _ => StatusCode::BAD_REQUEST,
         }
     }
 }
 
+/// The kind of limit reported by [`Error::LimitExceeded`].
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LimitKind {
+    /// The total size of the form, see [`MultipartFormConfig::total_limit`].
+    #[display(fmt = "total size")]
+    Total,
+    /// The data read into memory, see [`MultipartFormConfig::memory_limit`].
+    #[display(fmt = "memory")]
+    Memory,
+    /// The data written to disk, see [`MultipartFormConfig::disk_limit`].
+    #[display(fmt = "disk")]
+    Disk,
+    /// The size of a field, as set by the `#[multipart(limit="")]` attribute.
+    #[display(fmt = "field size")]
+    Field,
+    /// The number of parts, see [`MultipartFormConfig::part_limit`].
+    #[display(fmt = "part count")]
+    Parts,
+    /// The number of file parts, see [`MultipartFormConfig::file_limit`].
+    #[display(fmt = "file count")]
+    Files,
+    /// The length of a field name, see [`MultipartFormConfig::field_name_length_limit`].
+    #[display(fmt = "field name length")]
+    FieldNameLength,
+    /// The length of a filename, see [`MultipartFormConfig::file_name_length_limit`].
+    #[display(fmt = "filename length")]
+    FileNameLength,
+    /// The size of a part's headers, see [`MultipartFormConfig::header_size_limit`].
+    #[display(fmt = "header size")]
+    HeaderSize,
+    /// The decompressed size of a part, see [`MultipartFormConfig::decompressed_limit`].
+    #[display(fmt = "decompressed size")]
+    Decompressed,
+    /// The ratio of decompressed to compressed size of a part, see
+    /// [`MultipartFormConfig::max_compression_ratio`].
+    #[display(fmt = "compression ratio")]
+    CompressionRatio,
+}
+
 /// Trait that data types to be used in a multipart form struct should implement.
 ///
 /// It represents an asynchronous handler that processes a multipart field to produce `Self`.
@@ -185,6 +442,12 @@ pub trait FieldReader<'t>: Sized + Any {
 
     /// The form will call this function to handle the field.
     fn read_field(req: &'t HttpRequest, field: Field, limits: &'t mut Limits) -> Self::Future;
+
+    /// Whether this type can only be read from a file upload. These fields are skipped when a form
+    /// is submitted as `application/x-www-form-urlencoded`, and so will be reported as missing.
+    fn file_only() -> bool {
+        false
+    }
 }
 
 /// Used to accumulate the state of the loaded fields.
@@ -210,6 +473,31 @@ pub trait FieldGroupReader<'t>: Sized + Any {
     fn from_state(name: &str, state: &'t mut State) -> Result<Self, Error>;
 }
 
+/// Whether the field should be skipped because it was read from an urlencoded body, which cannot
+/// contain the file uploads that `T` requires.
+fn skip_urlencoded<'t, T: FieldReader<'t>>(field: &Field) -> bool {
+    field.is_urlencoded() && T::file_only()
+}
+
+/// Whether the field has a valid content type for a field reader, if `validate` is set. Values of a
+/// urlencoded form don't have a content type, so are always valid.
+pub(crate) fn is_valid_content_type(
+    field: &Field,
+    validate: bool,
+    valid: impl FnOnce(&mime::Mime) -> bool,
+) -> bool {
+    if !validate || field.is_urlencoded() {
+        return true;
+    }
+    field.content_type().is_some_and(valid)
+}
+
+/// Whether a content type is of a format, either with one of the format's subtypes (e.g.
+/// `application/cbor`) or with its structured syntax suffix (e.g. `application/senml+cbor`).
+pub(crate) fn is_format(mime: &mime::Mime, subtypes: &[&str], suffix: &str) -> bool {
+    subtypes.contains(&mime.subtype().as_str()) || mime.suffix().is_some_and(|s| s == suffix)
+}
+
 impl<'t, T> FieldGroupReader<'t> for Option<T>
 where
     T: FieldReader<'t>,
@@ -223,6 +511,9 @@ where
         state: &'t mut State,
         duplicate_action: DuplicateAction,
     ) -> Self::Future {
+        if skip_urlencoded::<T>(&field) {
+            return ready(Ok(())).boxed_local();
+        }
         if state.contains_key(field.name()) {
             match duplicate_action {
                 DuplicateAction::Ignore => return ready(Ok(())).boxed_local(),
@@ -235,8 +526,13 @@ where
         }
         async move {
             let field_name = field.name().to_string();
-            let t = T::read_field(req, field, limits).await?;
-            state.insert(field_name, Box::new(t));
+            match T::read_field(req, field, limits).await {
+                Ok(t) => {
+                    state.insert(field_name, Box::new(t));
+                }
+                Err(err) if err.is_not_sent() => {}
+                Err(err) => return Err(err),
+            }
             Ok(())
         }
         .boxed_local()
@@ -260,16 +556,22 @@ where
         state: &'t mut State,
         _duplicate_action: DuplicateAction,
     ) -> Self::Future {
+        if skip_urlencoded::<T>(&field) {
+            return ready(Ok(())).boxed_local();
+        }
         // Vec GroupReader always allows duplicates!
         async move {
             let field_name = field.name().to_string();
             let vec = state
-                .entry(field_name)
+                .entry(field_name.clone())
                 .or_insert_with(|| Box::new(Vec::<T>::new()))
                 .downcast_mut::<Vec<T>>()
                 .unwrap();
-            let item = T::read_field(req, field, limits).await?;
-            vec.push(item);
+            match T::read_field(req, field, limits).await {
+                Ok(item) => vec.push(item),
+                Err(err) if err.is_not_sent() => {}
+                Err(err) => return Err(err),
+            }
             Ok(())
         }
         .boxed_local()
@@ -296,6 +598,9 @@ where
         state: &'t mut State,
         duplicate_action: DuplicateAction,
     ) -> Self::Future {
+        if skip_urlencoded::<T>(&field) {
+            return ready(Ok(())).boxed_local();
+        }
         if state.contains_key(field.name()) {
             match duplicate_action {
                 DuplicateAction::Ignore => return ready(Ok(())).boxed_local(),
@@ -308,7 +613,11 @@ where
         }
         async move {
             let field_name = field.name().to_string();
-            let t = T::read_field(req, field, limits).await?;
+            let t = match T::read_field(req, field, limits).await {
+                Ok(t) => t,
+                Err(err) if err.is_not_sent() => return Err(Error::MissingField(field_name)),
+                Err(err) => return Err(err),
+            };
             state.insert(field_name, Box::new(t));
             Ok(())
         }
@@ -342,6 +651,29 @@ pub trait MultipartFormTrait: Sized {
     /// Once all the fields have been processed and stored in the state, this is called
     /// to convert into the struct representation.
     fn from_state(state: State) -> Result<Self, Error>;
+
+    /// Whether field errors should be collected into an [`Error::Multiple`], rather than returned
+    /// immediately.
+    fn accumulate_errors() -> bool {
+        false
+    }
+
+    /// Get the name of the field matching a part of a `multipart/mixed` or `multipart/related`
+    /// body, if any, see the [`related`] module.
+    fn match_part(_part: &related::PartInfo) -> Option<&'static str> {
+        None
+    }
+
+    /// The allowed content types for a given field name, if any, see the [`sniff`] module. Each
+    /// must be a valid content type, which is checked by the derive macro.
+    fn content_types(_field_name: &str) -> Option<&'static [&'static str]> {
+        None
+    }
+
+    /// The text options for a given field name, if any, see [`text::TextOptions`].
+    fn text_options(_field_name: &str) -> Option<text::TextOptions> {
+        None
+    }
 }
 
 #[doc(hidden)]
@@ -358,7 +690,20 @@ pub enum DuplicateAction {
 pub struct Limits {
     pub total_limit_remaining: usize,
     pub memory_limit_remaining: usize,
+    pub disk_limit_remaining: usize,
+    pub part_limit_remaining: usize,
+    pub file_limit_remaining: usize,
     pub field_limit_remaining: Option<usize>,
+    pub field_name_length_limit: usize,
+    pub file_name_length_limit: usize,
+    pub header_size_limit: usize,
+    total_limit: usize,
+    memory_limit: usize,
+    disk_limit: usize,
+    part_limit: usize,
+    file_limit: usize,
+    field_limit: Option<usize>,
+    field_name: Option<String>,
 }
 
 impl Limits {
@@ -366,8 +711,93 @@ impl Limits {
         Self {
             total_limit_remaining: total_limit,
             memory_limit_remaining: memory_limit,
+            disk_limit_remaining: usize::MAX,
+            part_limit_remaining: usize::MAX,
+            file_limit_remaining: usize::MAX,
             field_limit_remaining: None,
+            field_name_length_limit: usize::MAX,
+            file_name_length_limit: usize::MAX,
+            header_size_limit: usize::MAX,
+            total_limit,
+            memory_limit,
+            disk_limit: usize::MAX,
+            part_limit: usize::MAX,
+            file_limit: usize::MAX,
+            field_limit: None,
+            field_name: None,
+        }
+    }
+
+    /// Sets the field that subsequent calls will be attributed to, along with its limit and the
+    /// amount of the limit remaining (which is shared by all fields with the same name).
+    pub(crate) fn set_field(&mut self, name: &str, limit: Option<usize>, remaining: Option<usize>) {
+        self.field_name = Some(name.to_owned());
+        self.field_limit = limit;
+        self.field_limit_remaining = remaining;
+    }
+
+    /// The total number of bytes consumed so far.
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    pub(crate) fn bytes_consumed(&self) -> usize {
+        self.total_limit - self.total_limit_remaining
+    }
+
+    fn exceeded(&self, kind: LimitKind, limit: usize) -> Error {
+        Error::LimitExceeded {
+            kind,
+            field_name: self.field_name.clone(),
+            limit,
+        }
+    }
+
+    /// This function is called by the form for each part, before any of its data is read, to
+    /// ensure that the structural limits are not exceeded.
+    pub(crate) fn try_consume_part(&mut self, field: &Field) -> Result<(), Error> {
+        self.part_limit_remaining = self
+            .part_limit_remaining
+            .checked_sub(1)
+            .ok_or_else(|| self.exceeded(LimitKind::Parts, self.part_limit))?;
+        if field.name().len() > self.field_name_length_limit {
+            return Err(self.exceeded(LimitKind::FieldNameLength, self.field_name_length_limit));
+        }
+        let header_size: usize = field
+            .headers()
+            .iter()
+            .map(|(name, value)| name.as_str().len() + value.len())
+            .sum();
+        if header_size > self.header_size_limit {
+            return Err(self.exceeded(LimitKind::HeaderSize, self.header_size_limit));
+        }
+        if let Some(file_name) = field.file_name() {
+            self.file_limit_remaining = self
+                .file_limit_remaining
+                .checked_sub(1)
+                .ok_or_else(|| self.exceeded(LimitKind::Files, self.file_limit))?;
+            if file_name.len() > self.file_name_length_limit {
+                return Err(self.exceeded(LimitKind::FileNameLength, self.file_name_length_limit));
+            }
+        }
+        Ok(())
+    }
+
+    /// This function should be called within a [`FieldReader`] when writing each chunk of a field
+    /// to disk, in addition to [`Limits::try_consume_limits`], to ensure that the disk limit is not
+    /// exceeded.
+    pub fn try_consume_disk_limits(&mut self, bytes: usize) -> Result<(), Error> {
+        self.disk_limit_remaining = self
+            .disk_limit_remaining
+            .checked_sub(bytes)
+            .ok_or_else(|| self.exceeded(LimitKind::Disk, self.disk_limit))?;
+        Ok(())
+    }
+
+    /// Checks that a temporary allocation of the given size, such as when decoding a field, would
+    /// not exceed the memory limit. The memory is not consumed.
+    pub fn check_memory(&self, bytes: usize) -> Result<(), Error> {
+        if bytes > self.memory_limit_remaining {
+            return Err(self.exceeded(LimitKind::Memory, self.memory_limit));
         }
+        Ok(())
     }
 
     /// This function should be called within a [`FieldReader`] when reading each chunk of a field
@@ -381,19 +811,17 @@ impl Limits {
         self.total_limit_remaining = self
             .total_limit_remaining
             .checked_sub(bytes)
-            .ok_or(MultipartError::Payload(PayloadError::Overflow))?;
+            .ok_or_else(|| self.exceeded(LimitKind::Total, self.total_limit))?;
         if in_memory {
             self.memory_limit_remaining = self
                 .memory_limit_remaining
                 .checked_sub(bytes)
-                .ok_or(MultipartError::Payload(PayloadError::Overflow))?;
+                .ok_or_else(|| self.exceeded(LimitKind::Memory, self.memory_limit))?;
         }
         if let Some(field_limit) = self.field_limit_remaining {
-            self.field_limit_remaining = Some(
-                field_limit
-                    .checked_sub(bytes)
-                    .ok_or(MultipartError::Payload(PayloadError::Overflow))?,
-            );
+            self.field_limit_remaining = Some(field_limit.checked_sub(bytes).ok_or_else(|| {
+                self.exceeded(LimitKind::Field, self.field_limit.unwrap_or_default())
+            })?);
         }
         Ok(())
     }
@@ -405,6 +833,11 @@ impl Limits {
 /// [`MultipartFormTrait`] trait, you should use the [`macro@MultipartForm`] macro to derive this for
 /// your struct.
 ///
+/// An `application/x-www-form-urlencoded` body is also accepted, in which case each name/value
+/// pair is read as a field without a content type or filename, see [`Field::is_urlencoded`].
+/// Fields that can only be read from a file upload (see [`FieldReader::file_only`]) are skipped,
+/// and so are reported as missing.
+///
 /// Use [`MultipartFormConfig`] to configure extraction options.
 #[derive(Deref, DerefMut)]
 pub struct MultipartForm<T: MultipartFormTrait>(pub T);
@@ -425,42 +858,226 @@ where
 
     #[inline]
     fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
-        let mut payload = Multipart::new(req.headers(), payload.take());
-        let config = MultipartFormConfig::from_req(req);
-        let mut limits = Limits::new(config.total_limit, config.memory_limit);
+        let body = if req.content_type() == mime::APPLICATION_WWW_FORM_URLENCODED.essence_str() {
+            Body::Urlencoded
+        } else {
+            Body::Multipart(Field::from_form_data)
+        };
         let req = req.clone();
-        let req2 = req.clone();
-        let err_handler = config.err_handler.clone();
+        let payload = payload.take();
+        async move { Ok(MultipartForm(extract(req, payload, body).await?)) }.boxed_local()
+    }
+}
 
-        async move {
-            let mut state = State::default();
-            // We need to ensure field limits are shared for all instances of this field name
-            let mut field_limits = HashMap::<String, Option<usize>>::new();
+/// The type of request body to extract a form from.
+pub(crate) enum Body<F> {
+    /// A multipart body, using `F` to convert each part into a [`Field`].
+    Multipart(F),
+    /// An `application/x-www-form-urlencoded` body.
+    Urlencoded,
+}
 
-            while let Some(field) = payload.try_next().await? {
-                // Retrieve the limit for this field
-                let entry = field_limits
-                    .entry(field.name().to_owned())
-                    .or_insert_with(|| T::limit(field.name()));
-                limits.field_limit_remaining = entry.to_owned();
+/// Extract `T` from the request body.
+pub(crate) async fn extract<T, F>(
+    req: HttpRequest,
+    payload: Payload,
+    body: Body<F>,
+) -> Result<T, actix_web::Error>
+where
+    T: MultipartFormTrait,
+    F: FnMut(parser::Part) -> Result<Field, MultipartError>,
+{
+    let config = MultipartFormConfig::from_req(&req);
+    let mut limits = config.limits();
+    let recorder = telemetry::FormRecorder::start::<T>();
+
+    let mut form = FormState {
+        decode_transfer_encoding: config.decode_transfer_encoding,
+        decompression: config.decompression,
+        sniffing: config.sniffing.clone(),
+        ..FormState::default()
+    };
+    let result = match body {
+        Body::Multipart(to_field) => match parser::boundary(req.headers()) {
+            Ok(boundary) => {
+                let payload = parser::Multipart::new(&boundary, payload);
+                recorder
+                    .instrument(read_form::<T, F>(
+                        &req,
+                        payload,
+                        &mut limits,
+                        to_field,
+                        &mut form,
+                    ))
+                    .await
+            }
+            Err(err) => Err(err.into()),
+        },
+        Body::Urlencoded => {
+            recorder
+                .instrument(urlencoded::read_form::<T>(
+                    &req,
+                    payload,
+                    &mut limits,
+                    &mut form,
+                ))
+                .await
+        }
+    }
+    .and_then(|_| form.finish());
+    recorder.finish(&limits, &result);
 
-                T::handle_field(&req, field, &mut limits, &mut state).await?;
+    result.map_err(|e| match &config.err_handler {
+        Some(handler) => (*handler)(e, &req),
+        None => e.into(),
+    })
+}
 
-                // Update the stored limit
-                *entry = limits.field_limit_remaining;
-            }
-            let inner = T::from_state(state)?;
-            Ok(MultipartForm(inner))
+/// The state of a form while its fields are being read.
+#[derive(Default)]
+pub(crate) struct FormState {
+    state: State,
+    errors: Vec<Error>,
+    /// The names of the fields whose readers returned one of the accumulated errors.
+    failed_fields: HashSet<String>,
+    // We need to ensure field limits are shared for all instances of this field name
+    field_limits: HashMap<String, Option<usize>>,
+    decode_transfer_encoding: bool,
+    decompression: decompress::Decompression,
+    sniffing: sniff::Sniffing,
+    /// The value of the `_charset_` field, if any.
+    charset: Option<String>,
+    /// The values of the digest fields by the name of the field they are for, or `None` once that
+    /// field has been read.
+    #[cfg(feature = "digest")]
+    digest_fields: HashMap<String, Option<String>>,
+}
+
+impl FormState {
+    /// Convert the state into `T`, returning any accumulated errors.
+    fn finish<T: MultipartFormTrait>(self) -> Result<T, Error> {
+        let FormState {
+            state,
+            mut errors,
+            failed_fields,
+            ..
+        } = self;
+        if errors.is_empty() {
+            return T::from_state(state);
         }
-        .map_err(move |e| {
-            if let Some(handler) = err_handler {
-                (*handler)(e, &req2)
-            } else {
-                e.into()
+        // A field that failed to be read has already been reported, so it isn't also missing
+        let missing = match T::from_state(state) {
+            Ok(_) => vec![],
+            Err(Error::Multiple(missing)) => missing,
+            Err(err) => vec![err],
+        };
+        errors.extend(missing.into_iter().filter(|err| {
+            !matches!(err, Error::MissingField(field_name) if failed_fields.contains(field_name))
+        }));
+        Err(Error::Multiple(errors))
+    }
+}
+
+/// Process each part of a multipart body into the state.
+async fn read_form<T, F>(
+    req: &HttpRequest,
+    mut payload: parser::Multipart,
+    limits: &mut Limits,
+    mut to_field: F,
+    form: &mut FormState,
+) -> Result<(), Error>
+where
+    T: MultipartFormTrait,
+    F: FnMut(parser::Part) -> Result<Field, MultipartError>,
+{
+    while let Some(part) = payload.try_next().await? {
+        let field = to_field(part)?;
+        match field.nested_boundary()? {
+            Some(boundary) => {
+                // The container part counts towards the structural limits, as well as each of its
+                // parts, which are read as fields of the same name
+                let name = field.name().to_owned();
+                limits.set_field(&name, None, None);
+                limits.try_consume_part(&field)?;
+                let mut nested = parser::Multipart::new(&boundary, field);
+                while let Some(part) = nested.try_next().await? {
+                    let field = Field::from_part(name.clone(), part);
+                    if field.nested_boundary()?.is_some() {
+                        return Err(MultipartError::Nested.into());
+                    }
+                    read_field::<T>(req, field, limits, form).await?;
+                }
             }
-        })
-        .boxed_local()
+            None => read_field::<T>(req, field, limits, form).await?,
+        }
     }
+    Ok(())
+}
+
+/// Process a single field into the state.
+pub(crate) async fn read_field<T: MultipartFormTrait>(
+    req: &HttpRequest,
+    field: Field,
+    limits: &mut Limits,
+    form: &mut FormState,
+) -> Result<(), Error> {
+    // Retrieve the limit for this field
+    let entry = form
+        .field_limits
+        .entry(field.name().to_owned())
+        .or_insert_with(|| T::limit(field.name()));
+    limits.set_field(field.name(), T::limit(field.name()), entry.to_owned());
+    limits.try_consume_part(&field)?;
+    let (field, encoded_len) = if form.decode_transfer_encoding {
+        transfer_encoding::decode(field, limits)?
+    } else {
+        (field, None)
+    };
+    let field = form.decompression.decompress(field)?;
+    let content_types = T::content_types(field.name());
+    let text_options = T::text_options(field.name());
+    let field = form.sniffing.sniff(field, content_types).await?;
+
+    // The `_charset_` field sets the default charset for the text fields that follow it
+    let field = if field.name() == "_charset_" {
+        // A charset name is short, so anything longer is not buffered
+        let (field, charset) = field.read_short_value(64).await?;
+        form.charset = charset;
+        field
+    } else {
+        field
+    }
+    .with_form_charset(form.charset.clone())
+    .with_text_options(text_options);
+    #[cfg(feature = "digest")]
+    let field = digest::read_digest_field(req, field, &mut form.digest_fields).await?;
+
+    let field_name = field.name().to_owned();
+    let total_limit_remaining = limits.total_limit_remaining;
+    let field_recorder = telemetry::FieldRecorder::start(&field, limits);
+    let result = field_recorder
+        .instrument(T::handle_field(req, field, limits, &mut form.state))
+        .await;
+    field_recorder.finish(limits, &result);
+
+    // The field reader consumes the decoded data, so consume the rest of the encoded data
+    if let Some(encoded_len) = encoded_len {
+        let consumed = total_limit_remaining - limits.total_limit_remaining;
+        limits.try_consume_limits(encoded_len.get().saturating_sub(consumed), false)?;
+    }
+
+    // Update the stored limit
+    *entry = limits.field_limit_remaining;
+
+    if let Err(err) = result {
+        if T::accumulate_errors() && err.is_accumulable() {
+            form.errors.push(err);
+            form.failed_fields.insert(field_name);
+        } else {
+            return Err(err);
+        }
+    }
+    Ok(())
 }
 
 type MultipartFormErrorHandler =
@@ -471,6 +1088,15 @@ type MultipartFormErrorHandler =
 pub struct MultipartFormConfig {
     total_limit: usize,
     memory_limit: usize,
+    disk_limit: usize,
+    part_limit: usize,
+    file_limit: usize,
+    field_name_length_limit: usize,
+    file_name_length_limit: usize,
+    header_size_limit: usize,
+    decode_transfer_encoding: bool,
+    decompression: decompress::Decompression,
+    sniffing: sniff::Sniffing,
     err_handler: MultipartFormErrorHandler,
 }
 
@@ -487,6 +1113,126 @@ impl MultipartFormConfig {
         self
     }
 
+    /// Set maximum accepted data that will be written to disk (e.g. by a
+    /// [`Tempfile`](crate::tempfile::Tempfile)), independent of the memory limit. By default there
+    /// is no limit other than the total limit.
+    pub fn disk_limit(mut self, disk_limit: usize) -> Self {
+        self.disk_limit = disk_limit;
+        self
+    }
+
+    /// Set maximum number of parts accepted in the form. By default there is no limit.
+    pub fn part_limit(mut self, part_limit: usize) -> Self {
+        self.part_limit = part_limit;
+        self
+    }
+
+    /// Set maximum number of file parts (i.e. parts with a `filename`) accepted in the form. By
+    /// default there is no limit.
+    pub fn file_limit(mut self, file_limit: usize) -> Self {
+        self.file_limit = file_limit;
+        self
+    }
+
+    /// Set maximum length in bytes of a part's field name. By default there is no limit.
+    pub fn field_name_length_limit(mut self, field_name_length_limit: usize) -> Self {
+        self.field_name_length_limit = field_name_length_limit;
+        self
+    }
+
+    /// Set maximum length in bytes of a part's filename. By default there is no limit.
+    pub fn file_name_length_limit(mut self, file_name_length_limit: usize) -> Self {
+        self.file_name_length_limit = file_name_length_limit;
+        self
+    }
+
+    /// Set maximum combined size in bytes of the names and values of a part's headers. By default
+    /// there is no limit, other than the parser's fixed limits of 32 headers and a 16 KiB header
+    /// section, which fail with a `413 Payload Too Large` multipart error.
+    pub fn header_size_limit(mut self, header_size_limit: usize) -> Self {
+        self.header_size_limit = header_size_limit;
+        self
+    }
+
+    /// Set whether parts with a `Content-Transfer-Encoding` of `base64` or `quoted-printable` are
+    /// decoded before being read, parts with any other encoding (except `7bit`, `8bit` and
+    /// `binary`) are rejected. By default the data is read verbatim.
+    ///
+    /// The limits are applied to the decoded data, and the encoded data is also consumed from the
+    /// total and field limits.
+    pub fn decode_transfer_encoding(mut self, decode_transfer_encoding: bool) -> Self {
+        self.decode_transfer_encoding = decode_transfer_encoding;
+        self
+    }
+
+    /// Set whether parts with a `Content-Encoding` header are decompressed before being read. By
+    /// default the data is read verbatim.
+    ///
+    /// The supported encodings depend on the enabled features: `gzip` and `deflate` require
+    /// `compress-gzip`, `br` requires `compress-brotli` and `zstd` requires `compress-zstd`. A
+    /// part with any other encoding is rejected with a
+    /// [`DecompressError`](decompress::DecompressError).
+    ///
+    /// The limits are applied to the decompressed data, see also
+    /// [`decompressed_limit`](Self::decompressed_limit) and
+    /// [`max_compression_ratio`](Self::max_compression_ratio).
+    pub fn decompress(mut self, decompress: bool) -> Self {
+        self.decompression.enabled = decompress;
+        self
+    }
+
+    /// Set whether parts with a filename ending in `.gz` (and no `Content-Encoding` header) are
+    /// decompressed as gzip, when [`decompress`](Self::decompress) is enabled. By default these
+    /// are read verbatim.
+    pub fn decompress_gz_files(mut self, decompress_gz_files: bool) -> Self {
+        self.decompression.by_file_name = decompress_gz_files;
+        self
+    }
+
+    /// Set maximum size in bytes of the decompressed data of each part, failing with
+    /// [`LimitKind::Decompressed`] once exceeded. By default there is no limit other than the
+    /// form's limits.
+    pub fn decompressed_limit(mut self, decompressed_limit: usize) -> Self {
+        self.decompression.limit = decompressed_limit;
+        self
+    }
+
+    /// Set maximum ratio of decompressed to compressed size of each part, to protect against
+    /// decompression bombs, failing with [`LimitKind::CompressionRatio`] once exceeded. The ratio
+    /// is only checked once 64KiB of a part has been decompressed. By default this is 100.
+    pub fn max_compression_ratio(mut self, max_compression_ratio: usize) -> Self {
+        self.decompression.max_ratio = max_compression_ratio;
+        self
+    }
+
+    /// Set whether the content type of each part is detected from its first bytes, see the
+    /// [`sniff`] module. By default only fields with a `#[multipart(content_types = "")]`
+    /// attribute are sniffed.
+    pub fn sniff_content_type(mut self, sniff_content_type: bool) -> Self {
+        self.sniffing.enabled = sniff_content_type;
+        self
+    }
+
+    /// Set whether a part is rejected with a [`SniffError`](sniff::SniffError) if its detected
+    /// content type disagrees with its declared `Content-Type`, when
+    /// [`sniff_content_type`](Self::sniff_content_type) is enabled. A part declared as
+    /// `application/octet-stream` is never rejected. By default mismatches are allowed.
+    pub fn reject_content_type_mismatch(mut self, reject_content_type_mismatch: bool) -> Self {
+        self.sniffing.reject_mismatch = reject_content_type_mismatch;
+        self
+    }
+
+    /// Set the content types allowed for files (parts with a filename), which may include
+    /// wildcards such as `image/*`. The detected content type is checked if there is one, else the
+    /// declared content type. Fields with a `#[multipart(content_types = "")]` attribute use
+    /// their own allowlist instead. Only used when
+    /// [`sniff_content_type`](Self::sniff_content_type) is enabled. By default all content types
+    /// are allowed.
+    pub fn allowed_content_types(mut self, allowed_content_types: Vec<mime::Mime>) -> Self {
+        self.sniffing.allowed = Some(allowed_content_types);
+        self
+    }
+
     /// Set custom error handler.
     pub fn error_handler<F>(mut self, f: F) -> Self
     where
@@ -496,6 +1242,12 @@ impl MultipartFormConfig {
         self
     }
 
+    /// Render errors as `application/problem+json` responses, this replaces any custom error
+    /// handler. See [`ProblemDetails`](problem::ProblemDetails) for the format.
+    pub fn problem_details(self, problem_details: problem::ProblemDetails) -> Self {
+        self.error_handler(move |err, _| problem_details.map_error(err))
+    }
+
     /// Extract payload config from app data. Check both `T` and `Data<T>`, in that order, and fall
     /// back to the default payload config.
     fn from_req(req: &HttpRequest) -> &Self {
@@ -503,11 +1255,45 @@ impl MultipartFormConfig {
             .or_else(|| req.app_data::<web::Data<Self>>().map(|d| d.as_ref()))
             .unwrap_or(&DEFAULT_CONFIG)
     }
+
+    /// Create the initial [`Limits`] for a form.
+    fn limits(&self) -> Limits {
+        Limits {
+            disk_limit_remaining: self.disk_limit,
+            part_limit_remaining: self.part_limit,
+            file_limit_remaining: self.file_limit,
+            field_name_length_limit: self.field_name_length_limit,
+            file_name_length_limit: self.file_name_length_limit,
+            header_size_limit: self.header_size_limit,
+            disk_limit: self.disk_limit,
+            part_limit: self.part_limit,
+            file_limit: self.file_limit,
+            ..Limits::new(self.total_limit, self.memory_limit)
+        }
+    }
 }
 
 const DEFAULT_CONFIG: MultipartFormConfig = MultipartFormConfig {
     total_limit: 52_428_800, // 50 MiB
     memory_limit: 2_097_152, // 2 MiB
+    disk_limit: usize::MAX,
+    part_limit: usize::MAX,
+    file_limit: usize::MAX,
+    field_name_length_limit: usize::MAX,
+    file_name_length_limit: usize::MAX,
+    header_size_limit: usize::MAX,
+    decode_transfer_encoding: false,
+    decompression: decompress::Decompression {
+        enabled: false,
+        by_file_name: false,
+        limit: usize::MAX,
+        max_ratio: 100,
+    },
+    sniffing: sniff::Sniffing {
+        enabled: false,
+        reject_mismatch: false,
+        allowed: None,
+    },
     err_handler: None,
 };
 
@@ -517,21 +1303,13 @@ impl Default for MultipartFormConfig {
     }
 }
 
-// Work around until https://github.com/actix/actix-web/pull/2885 becomes available
-fn field_mime(field: &Field) -> Option<mime::Mime> {
-    if field.headers().get(&header::CONTENT_TYPE).is_none() {
-        None
-    } else {
-        Some(field.content_type().clone())
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::MultipartForm;
     use crate::bytes::Bytes;
+    use crate::json::Json;
     use crate::text::Text;
-    use crate::MultipartFormConfig;
+    use crate::{Error, LimitKind, MultipartFormConfig};
     use actix_http::encoding::Decoder;
     use actix_http::Payload;
     use actix_multipart_rfc7578::client::multipart;
@@ -539,6 +1317,8 @@ mod tests {
     use actix_web::http::StatusCode;
     use actix_web::{web, App, HttpResponse, Responder};
     use awc::{Client, ClientResponse};
+    use std::collections::HashMap;
+    use std::io::Cursor;
 
     pub async fn send_form(
         srv: &TestServer,
@@ -611,6 +1391,252 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    /// Test nested `multipart/mixed` fields
+
+    #[derive(MultipartForm)]
+    struct TestNestedMixed {
+        field: Vec<Bytes>,
+    }
+
+    async fn test_nested_mixed_route(form: MultipartForm<TestNestedMixed>) -> impl Responder {
+        assert_eq!(form.field.len(), 3);
+        assert_eq!(form.field[0].data, "file one");
+        assert_eq!(form.field[0].file_name.as_deref(), Some("file1.txt"));
+        assert_eq!(form.field[0].content_type, Some(mime::TEXT_PLAIN));
+        assert_eq!(form.field[1].data, "file two");
+        assert_eq!(form.field[1].file_name.as_deref(), Some("file2.txt"));
+        assert_eq!(form.field[2].data, "file three");
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_nested_mixed() {
+        let srv =
+            actix_test::start(|| App::new().route("/", web::post().to(test_nested_mixed_route)));
+
+        let body = "--AaB03x\r\n\
+            Content-Disposition: form-data; name=\"field\"\r\n\
+            Content-Type: multipart/mixed; boundary=BbC04y\r\n\
+            \r\n\
+            --BbC04y\r\n\
+            Content-Disposition: file; filename=\"file1.txt\"\r\n\
+            Content-Type: text/plain\r\n\
+            \r\n\
+            file one\r\n\
+            --BbC04y\r\n\
+            Content-Disposition: file; filename=\"file2.txt\"\r\n\
+            \r\n\
+            file two\r\n\
+            --BbC04y--\r\n\
+            --AaB03x\r\n\
+            Content-Disposition: form-data; name=\"field\"; filename=\"file3.txt\"\r\n\
+            \r\n\
+            file three\r\n\
+            --AaB03x--\r\n";
+        let send = |body: String| {
+            Client::default()
+                .post(srv.url("/"))
+                .content_type("multipart/form-data; boundary=AaB03x")
+                .send_body(body)
+        };
+
+        let response = send(body.to_owned()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Other multipart subtypes are read in the same way
+        let response = send(body.replace("multipart/mixed", "multipart/alternative"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // A nested part may not contain another multipart body
+        let nested = body.replace(
+            "Content-Type: text/plain",
+            "Content-Type: multipart/mixed; boundary=CcD05z",
+        );
+        let response = send(nested).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // The container part counts towards the structural limits, as well as each nested part
+        for (config, status) in [
+            (MultipartFormConfig::default().part_limit(4), StatusCode::OK),
+            (
+                MultipartFormConfig::default().part_limit(3),
+                StatusCode::PAYLOAD_TOO_LARGE,
+            ),
+            (
+                MultipartFormConfig::default().header_size_limit(80),
+                StatusCode::PAYLOAD_TOO_LARGE,
+            ),
+        ] {
+            let srv = actix_test::start(move || {
+                App::new()
+                    .route("/", web::post().to(test_nested_mixed_route))
+                    .app_data(config.clone())
+            });
+            let response = Client::default()
+                .post(srv.url("/"))
+                .content_type("multipart/form-data; boundary=AaB03x")
+                .send_body(body)
+                .await
+                .unwrap();
+            assert_eq!(response.status(), status);
+        }
+    }
+
+    /// Test `application/x-www-form-urlencoded` bodies
+
+    #[derive(MultipartForm)]
+    struct TestUrlencoded {
+        name: Text<String>,
+        json: Json<HashMap<String, i32>>,
+        data: Bytes,
+        list: Vec<Text<i32>>,
+    }
+
+    async fn test_urlencoded_route(form: MultipartForm<TestUrlencoded>) -> impl Responder {
+        assert_eq!(&*form.name, "a b&c");
+        assert_eq!(form.json.get("key"), Some(&1));
+        assert_eq!(form.data.data, "raw");
+        assert_eq!(form.data.content_type, None);
+        let list = form.list.iter().map(|t| **t).collect::<Vec<_>>();
+        assert_eq!(list, vec![1, 2]);
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_urlencoded() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_urlencoded_route))
+                .app_data(MultipartFormConfig::default().total_limit(100))
+        });
+
+        let response = Client::default()
+            .post(srv.url("/"))
+            .send_form(&[
+                ("name", "a b&c"),
+                ("json", r#"{"key":1}"#),
+                ("data", "raw"),
+                ("list", "1"),
+                ("list", "2"),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Missing field
+        let response = Client::default()
+            .post(srv.url("/"))
+            .send_form(&[("name", "a b&c")])
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Exceeds the 100 byte overall limit
+        let response = Client::default()
+            .post(srv.url("/"))
+            .send_form(&[("name", "a".repeat(100))])
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[derive(MultipartForm)]
+    struct TestUrlencodedMemory {
+        _name: Text<String>,
+    }
+
+    async fn test_urlencoded_memory_route(
+        _: MultipartForm<TestUrlencodedMemory>,
+    ) -> impl Responder {
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_urlencoded_memory_limit() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_urlencoded_memory_route))
+                .app_data(MultipartFormConfig::default().memory_limit(100))
+        });
+
+        let response = Client::default()
+            .post(srv.url("/"))
+            .send_form(&[("_name", "a".repeat(40))])
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The body and the decoded value are both held in memory
+        let response = Client::default()
+            .post(srv.url("/"))
+            .send_form(&[("_name", "a".repeat(60))])
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    /// Test `Content-Transfer-Encoding` decoding
+
+    #[derive(MultipartForm)]
+    struct TestTransferEncoding {
+        text: Text<String>,
+        data: Bytes,
+    }
+
+    async fn test_transfer_encoding_route(
+        form: MultipartForm<TestTransferEncoding>,
+    ) -> impl Responder {
+        assert_eq!(&*form.text, "Hello, world!");
+        assert_eq!(form.data.data, "café");
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_transfer_encoding() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_transfer_encoding_route))
+                .app_data(
+                    MultipartFormConfig::default()
+                        .decode_transfer_encoding(true)
+                        .total_limit(40),
+                )
+        });
+
+        let send = |padding: &str| {
+            let body = format!(
+                "--AaB03x\r\n\
+                Content-Disposition: form-data; name=\"text\"\r\n\
+                Content-Transfer-Encoding: base64\r\n\
+                \r\n\
+                SGVsbG8s\r\nIHdvcmxkIQ==\r\n{padding}\r\n\
+                --AaB03x\r\n\
+                Content-Disposition: form-data; name=\"data\"\r\n\
+                Content-Transfer-Encoding: quoted-printable\r\n\
+                \r\n\
+                caf=C3=A9\r\n\
+                --AaB03x--\r\n"
+            );
+            Client::default()
+                .post(srv.url("/"))
+                .content_type("multipart/form-data; boundary=AaB03x")
+                .send_body(body)
+        };
+
+        let response = send("").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The encoded data exceeds the 40 byte overall limit
+        let response = send(&" ".repeat(20)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // The encoded data of the first field is consumed, so the second field exceeds the limit
+        let response = send(&" ".repeat(10)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
     /// Test the `rename` field attribute
 
     #[derive(MultipartForm)]
@@ -745,6 +1771,55 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    /// Test the `accumulate_errors` struct attribute
+
+    #[derive(MultipartForm)]
+    #[multipart(accumulate_errors)]
+    struct TestAccumulateErrors {
+        number: Text<i32>,
+        _invalid: Text<i32>,
+        _missing: Text<String>,
+    }
+
+    async fn test_accumulate_errors_route(
+        form: MultipartForm<TestAccumulateErrors>,
+    ) -> impl Responder {
+        assert_eq!(*form.number, 1);
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_accumulate_errors() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_accumulate_errors_route))
+                .app_data(MultipartFormConfig::default().error_handler(|err, _| {
+                    match &err {
+                        Error::Multiple(errors) => {
+                            assert_eq!(errors.len(), 2);
+                            assert!(matches!(&errors[0], Error::Field { field_name, .. } if field_name == "_invalid"));
+                            assert!(matches!(&errors[1], Error::MissingField(field_name) if field_name == "_missing"));
+                        }
+                        _ => panic!("Unexpected error: {err}"),
+                    }
+                    actix_web::error::ErrorBadRequest(err.to_string())
+                }))
+        });
+
+        let mut form = multipart::Form::default();
+        form.add_text("number", "1");
+        form.add_text("_invalid", "not a number");
+        let response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let mut form = multipart::Form::default();
+        form.add_text("number", "1");
+        form.add_text("_invalid", "2");
+        form.add_text("_missing", "value");
+        let response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
     /// Test the Limits
 
     #[derive(MultipartForm)]
@@ -789,7 +1864,7 @@ mod tests {
         let mut form = multipart::Form::default();
         form.add_text("field", "this string is 28 bytes long");
         let response = send_form(&srv, form, "/text").await;
-        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
 
         // Memory limit should not apply when the data is being streamed to disk
         let mut form = multipart::Form::default();
@@ -822,13 +1897,13 @@ mod tests {
         let mut form = multipart::Form::default();
         form.add_text("field", "this string is 28 bytes long");
         let response = send_form(&srv, form, "/text").await;
-        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
 
         // Exceeds the 20 byte overall limit
         let mut form = multipart::Form::default();
         form.add_text("field", "this string is 28 bytes long");
         let response = send_form(&srv, form, "/file").await;
-        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
     }
 
     #[derive(MultipartForm)]
@@ -866,7 +1941,7 @@ mod tests {
         let mut form = multipart::Form::default();
         form.add_text("field", "this string is more than 30 bytes long");
         let response = send_form(&srv, form, "/").await;
-        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
 
         // Total of values (14 bytes) is within 30 byte limit for "field"
         let mut form = multipart::Form::default();
@@ -880,6 +1955,164 @@ mod tests {
         form.add_text("field", "this string is 28 bytes long");
         form.add_text("field", "this string is 28 bytes long");
         let response = send_form(&srv, form, "/").await;
-        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_rt::test]
+    async fn test_limit_exceeded_error() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_field_level_limits_route))
+                .app_data(MultipartFormConfig::default().error_handler(|err, _| {
+                    match &err {
+                        Error::LimitExceeded {
+                            kind: LimitKind::Field,
+                            field_name: Some(field_name),
+                            limit: 30,
+                        } => assert_eq!(field_name, "field"),
+                        _ => panic!("Unexpected error: {err}"),
+                    }
+                    actix_web::error::ErrorPayloadTooLarge(err.to_string())
+                }))
+        });
+
+        let mut form = multipart::Form::default();
+        form.add_text("field", "this string is more than 30 bytes long");
+        let mut response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(
+            response.body().await.unwrap(),
+            "The field size limit of 30 was exceeded by field `field`"
+        );
+    }
+
+    #[derive(MultipartForm)]
+    struct TestStructuralLimits {
+        field: Vec<Bytes>,
+    }
+
+    async fn test_structural_limits_route(
+        form: MultipartForm<TestStructuralLimits>,
+    ) -> impl Responder {
+        assert!(!form.field.is_empty());
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_part_limits() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_structural_limits_route))
+                .app_data(MultipartFormConfig::default().part_limit(3).file_limit(1))
+        });
+
+        // Within the 3 part limit
+        let mut form = multipart::Form::default();
+        form.add_text("field", "value");
+        form.add_text("field", "value");
+        form.add_text("field", "value");
+        let response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Exceeds the 3 part limit
+        let mut form = multipart::Form::default();
+        form.add_text("field", "value");
+        form.add_text("field", "value");
+        form.add_text("field", "value");
+        form.add_text("field", "value");
+        let response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // Exceeds the 1 file limit
+        let mut form = multipart::Form::default();
+        form.add_reader_file("field", Cursor::new("value"), "file1.txt");
+        form.add_reader_file("field", Cursor::new("value"), "file2.txt");
+        let response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_rt::test]
+    async fn test_name_length_limits() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_structural_limits_route))
+                .app_data(
+                    MultipartFormConfig::default()
+                        .field_name_length_limit(5)
+                        .file_name_length_limit(8),
+                )
+        });
+
+        // Within the name length limits
+        let mut form = multipart::Form::default();
+        form.add_reader_file("field", Cursor::new("value"), "file.txt");
+        let response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Exceeds the 5 byte field name limit
+        let mut form = multipart::Form::default();
+        form.add_text("field", "value");
+        form.add_text("long_field", "value");
+        let response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // Exceeds the 8 byte filename limit
+        let mut form = multipart::Form::default();
+        form.add_reader_file("field", Cursor::new("value"), "long_file.txt");
+        let response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[actix_rt::test]
+    async fn test_header_size_limits() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_structural_limits_route))
+                .app_data(
+                    MultipartFormConfig::default()
+                        .header_size_limit(200)
+                        .error_handler(|err, _| {
+                            if let Error::LimitExceeded { kind, .. } = &err {
+                                assert_eq!(*kind, LimitKind::HeaderSize);
+                            }
+                            err.into()
+                        }),
+                )
+        });
+        let send = |headers: String| {
+            let body = format!(
+                "--abc\r\n\
+                Content-Disposition: form-data; name=\"field\"\r\n\
+                {headers}\
+                \r\n\
+                value\r\n\
+                --abc--\r\n"
+            );
+            Client::default()
+                .post(srv.url("/"))
+                .content_type("multipart/form-data; boundary=abc")
+                .send_body(body)
+        };
+
+        // Within the 200 byte limit
+        let response = send(String::new()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Exceeds the 200 byte limit
+        let response = send(format!("X-Padding: {}\r\n", "a".repeat(200)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // Exceeds the parser's limit on the size of the header section
+        let response = send(format!("X-Padding: {}\r\n", "a".repeat(20_000)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // Exceeds the parser's limit on the number of headers
+        let headers = (0..40).map(|i| format!("X-{i}: a\r\n")).collect();
+        let response = send(headers).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
     }
 }
@@ -0,0 +1,193 @@
+//! Deserializes a field as MessagePack, enabled using the `msgpack` feature.
+use crate::bytes::Bytes;
+use crate::{is_format, is_valid_content_type, Field, FieldReader, Limits};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, ResponseError};
+use derive_more::{Deref, DerefMut, Display, Error};
+use futures_core::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+
+/// Deserialize from [MessagePack](https://msgpack.org/).
+#[derive(Debug, Deref, DerefMut)]
+pub struct MsgPack<T: DeserializeOwned>(pub T);
+
+impl<T: DeserializeOwned> MsgPack<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<'t, T: DeserializeOwned + 'static> FieldReader<'t> for MsgPack<T> {
+    type Future = LocalBoxFuture<'t, Result<Self, crate::Error>>;
+
+    fn read_field(req: &'t HttpRequest, mut field: Field, limits: &'t mut Limits) -> Self::Future {
+        async move {
+            let config = MsgPackConfig::from_req(req);
+            let field_name = field.name().to_owned();
+            let field_error = |err: MsgPackFieldError| crate::Error::Field {
+                field_name: field_name.clone(),
+                source: config.map_error(req, err),
+            };
+
+            if !is_valid_content_type(&field, config.validate_content_type, |mime| {
+                is_format(mime, &["msgpack", "x-msgpack", "vnd.msgpack"], "msgpack")
+            }) {
+                return Err(field_error(MsgPackFieldError::ContentType));
+            }
+
+            let bytes = Bytes::read_unscanned(req, &mut field, limits).await?;
+
+            let mut data = bytes.data.as_ref();
+            let value = rmp_serde::from_read(&mut data)
+                .map_err(|e| field_error(MsgPackFieldError::Deserialize(e)))?;
+            if !data.is_empty() {
+                return Err(field_error(MsgPackFieldError::TrailingData));
+            }
+            Ok(MsgPack(value))
+        }
+        .boxed_local()
+    }
+}
+
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum MsgPackFieldError {
+    /// Deserialize error
+    #[display(fmt = "MessagePack deserialize error: {}", _0)]
+    Deserialize(rmp_serde::decode::Error),
+
+    /// Content type error
+    #[display(fmt = "Content type error")]
+    ContentType,
+
+    /// There is data after the MessagePack value
+    #[display(fmt = "Trailing data after the MessagePack value")]
+    TrailingData,
+}
+
+impl ResponseError for MsgPackFieldError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// Configuration for the [`MsgPack`] field reader.
+#[derive(Clone)]
+pub struct MsgPackConfig {
+    err_handler:
+        Option<Arc<dyn Fn(MsgPackFieldError, &HttpRequest) -> actix_web::Error + Send + Sync>>,
+    validate_content_type: bool,
+}
+
+const DEFAULT_CONFIG: MsgPackConfig = MsgPackConfig {
+    err_handler: None,
+    validate_content_type: true,
+};
+
+impl MsgPackConfig {
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(MsgPackFieldError, &HttpRequest) -> actix_web::Error + Send + Sync + 'static,
+    {
+        self.err_handler = Some(Arc::new(f));
+        self
+    }
+
+    /// Extract payload config from app data. Check both `T` and `Data<T>`, in that order, and fall
+    /// back to the default payload config.
+    fn from_req(req: &HttpRequest) -> &Self {
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<web::Data<Self>>().map(|d| d.as_ref()))
+            .unwrap_or(&DEFAULT_CONFIG)
+    }
+
+    fn map_error(&self, req: &HttpRequest, err: MsgPackFieldError) -> actix_web::Error {
+        if let Some(err_handler) = self.err_handler.as_ref() {
+            (*err_handler)(err, req)
+        } else {
+            err.into()
+        }
+    }
+
+    /// Sets whether or not the field must have a valid `Content-Type` header to be parsed, which
+    /// has one of the `msgpack`, `x-msgpack` or `vnd.msgpack` subtypes (e.g. `application/msgpack`)
+    /// or the `+msgpack` suffix.
+    pub fn validate_content_type(mut self, validate_content_type: bool) -> Self {
+        self.validate_content_type = validate_content_type;
+        self
+    }
+}
+
+impl Default for MsgPackConfig {
+    fn default() -> Self {
+        DEFAULT_CONFIG
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::msgpack::{MsgPack, MsgPackConfig};
+    use crate::tests::send_form;
+    use crate::MultipartForm;
+    use actix_multipart_rfc7578::client::multipart;
+    use actix_web::http::StatusCode;
+    use actix_web::{web, App, HttpResponse, Responder};
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    #[derive(MultipartForm)]
+    struct MsgPackForm {
+        metadata: MsgPack<HashMap<String, u32>>,
+    }
+
+    async fn test_msgpack_route(form: MultipartForm<MsgPackForm>) -> impl Responder {
+        assert_eq!(form.metadata["temperature"], 21);
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_msgpack() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_msgpack_route))
+                .app_data(MsgPackConfig::default())
+        });
+        let data = rmp_serde::to_vec(&HashMap::from([("temperature", 21)])).unwrap();
+        let form = |data: Vec<u8>, mime: &str| {
+            let mut form = multipart::Form::default();
+            let mime = mime.parse().unwrap();
+            form.add_reader_file_with_mime("metadata", Cursor::new(data), "", mime);
+            form
+        };
+
+        for mime in [
+            "application/msgpack",
+            "application/vnd.msgpack",
+            "application/x-msgpack",
+        ] {
+            let response = send_form(&srv, form(data.clone(), mime), "/").await;
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        // Deny because wrong content type
+        let response = send_form(&srv, form(data.clone(), "application/json"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Deny because there is a second value after the map
+        let mut trailing = data.clone();
+        trailing.push(0x01);
+        let response = send_form(&srv, form(trailing, "application/msgpack"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Deny because the map is truncated
+        let response = send_form(
+            &srv,
+            form(data[..data.len() - 1].to_vec(), "application/msgpack"),
+            "/",
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,339 @@
+//! A streaming multipart body parser, see
+//! [RFC 2046 §5.1](https://www.rfc-editor.org/rfc/rfc2046#section-5.1).
+use actix_multipart::MultipartError;
+use actix_web::error::ParseError;
+use actix_web::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use bytes::{Buf, Bytes, BytesMut};
+use futures_core::stream::{LocalBoxStream, Stream};
+use futures_util::{StreamExt, TryStreamExt};
+use std::cell::RefCell;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{ready, Context, Poll};
+
+const MAX_HEADERS: usize = 32;
+
+/// The maximum size of a part's header section, or of the padding after a boundary delimiter.
+const MAX_HEADERS_SIZE: usize = 16_384; // 16 KiB
+
+/// Extract the boundary parameter from a multipart `Content-Type` header.
+pub(crate) fn boundary(headers: &HeaderMap) -> Result<String, MultipartError> {
+    headers
+        .get(&header::CONTENT_TYPE)
+        .ok_or(MultipartError::NoContentType)?
+        .to_str()
+        .ok()
+        .and_then(|content_type| content_type.parse::<mime::Mime>().ok())
+        .ok_or(MultipartError::ParseContentType)?
+        .get_param(mime::BOUNDARY)
+        .map(|boundary| boundary.as_str().to_owned())
+        .ok_or(MultipartError::Boundary)
+}
+
+/// A stream of the parts in a multipart body.
+pub(crate) struct Multipart {
+    inner: Rc<RefCell<Inner>>,
+}
+
+/// A part of a multipart body. Its body must be read before the next part is available, any
+/// unread data will be skipped.
+pub(crate) struct Part {
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: PartBody,
+}
+
+/// The body of a [`Part`].
+pub(crate) struct PartBody {
+    inner: Rc<RefCell<Inner>>,
+    index: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Skipping any data before the first boundary delimiter.
+    Preamble,
+    /// Reading the header section of a part.
+    Headers,
+    /// Reading the body of a part.
+    Body,
+    /// The close delimiter has been read.
+    Eof,
+}
+
+struct Inner {
+    stream: LocalBoxStream<'static, Result<Bytes, MultipartError>>,
+    buf: BytesMut,
+    eof: bool,
+    /// The boundary delimiter, including the preceding CRLF.
+    delimiter: Vec<u8>,
+    state: State,
+    /// The index of the current part, so that the body of a previous part can't be read.
+    index: usize,
+}
+
+impl Multipart {
+    pub(crate) fn new<S, E>(boundary: &str, stream: S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, E>> + 'static,
+        E: Into<MultipartError> + 'static,
+    {
+        let delimiter = [b"\r\n--", boundary.as_bytes()].concat();
+        Self {
+            inner: Rc::new(RefCell::new(Inner {
+                stream: stream.map_err(Into::into).boxed_local(),
+                // The CRLF preceding the first delimiter may be omitted
+                buf: BytesMut::from(&b"\r\n"[..]),
+                eof: false,
+                delimiter,
+                state: State::Preamble,
+                index: 0,
+            })),
+        }
+    }
+}
+
+impl Stream for Multipart {
+    type Item = Result<Part, MultipartError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut inner = self.inner.borrow_mut();
+        loop {
+            match inner.state {
+                // Skip the preamble, or any unread data from the previous part
+                State::Preamble | State::Body => {
+                    if let Some(Err(err)) = ready!(inner.poll_body(cx)) {
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                }
+                State::Headers => match inner.parse_headers() {
+                    Ok(Some(headers)) => {
+                        inner.state = State::Body;
+                        inner.index += 1;
+                        let body = PartBody {
+                            inner: Rc::clone(&self.inner),
+                            index: inner.index,
+                        };
+                        return Poll::Ready(Some(Ok(Part { headers, body })));
+                    }
+                    Ok(None) => {
+                        if !ready!(inner.poll_fill(cx))? {
+                            return Poll::Ready(Some(Err(MultipartError::Incomplete)));
+                        }
+                    }
+                    Err(err) => return Poll::Ready(Some(Err(err))),
+                },
+                State::Eof => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl Stream for PartBody {
+    type Item = Result<Bytes, MultipartError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut inner = self.inner.borrow_mut();
+        if inner.index != self.index || inner.state != State::Body {
+            return Poll::Ready(None);
+        }
+        inner.poll_body(cx)
+    }
+}
+
+impl Inner {
+    /// Read the next chunk from the stream into the buffer, returns `false` at the end of the
+    /// stream.
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<Result<bool, MultipartError>> {
+        if self.eof {
+            return Poll::Ready(Ok(false));
+        }
+        match ready!(self.stream.poll_next_unpin(cx)) {
+            Some(Ok(chunk)) => {
+                self.buf.extend_from_slice(&chunk);
+                Poll::Ready(Ok(true))
+            }
+            Some(Err(err)) => Poll::Ready(Err(err)),
+            None => {
+                self.eof = true;
+                Poll::Ready(Ok(false))
+            }
+        }
+    }
+
+    /// Read the next chunk of data up to the next boundary delimiter. Once the delimiter is
+    /// reached this returns `None`, and the state is updated to read the next part's headers or
+    /// the end of the body.
+    fn poll_body(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Bytes, MultipartError>>> {
+        loop {
+            match memchr::memmem::find(&self.buf, &self.delimiter) {
+                Some(0) => {
+                    let rest = &self.buf[self.delimiter.len()..];
+                    if rest.starts_with(b"--") {
+                        // The close delimiter, anything following it is the epilogue
+                        self.state = State::Eof;
+                        self.buf.clear();
+                        return Poll::Ready(None);
+                    }
+                    if let Some(end) = memchr::memmem::find(rest, b"\r\n") {
+                        // The delimiter may be followed by whitespace padding
+                        if !rest[..end].iter().all(|b| matches!(b, b' ' | b'\t')) {
+                            return Poll::Ready(Some(Err(MultipartError::Boundary)));
+                        }
+                        self.buf.advance(self.delimiter.len() + end + 2);
+                        self.state = State::Headers;
+                        return Poll::Ready(None);
+                    }
+                    if rest.len() > MAX_HEADERS_SIZE {
+                        return Poll::Ready(Some(Err(MultipartError::Boundary)));
+                    }
+                }
+                Some(pos) => return Poll::Ready(Some(Ok(self.buf.split_to(pos).freeze()))),
+                None => {
+                    // The end of the buffer may contain the start of a delimiter
+                    let len = self.buf.len().saturating_sub(self.delimiter.len() - 1);
+                    if len > 0 {
+                        return Poll::Ready(Some(Ok(self.buf.split_to(len).freeze())));
+                    }
+                }
+            }
+            match ready!(self.poll_fill(cx)) {
+                Ok(true) => {}
+                Ok(false) => return Poll::Ready(Some(Err(MultipartError::Incomplete))),
+                Err(err) => return Poll::Ready(Some(Err(err))),
+            }
+        }
+    }
+
+    /// Parse the header section of a part, returns `None` if more data is required.
+    fn parse_headers(&mut self) -> Result<Option<HeaderMap>, MultipartError> {
+        let mut hdrs = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let (len, headers) = match httparse::parse_headers(&self.buf, &mut hdrs) {
+            // The header section may have been received in one go
+            Ok(httparse::Status::Complete((len, _))) if len > MAX_HEADERS_SIZE => {
+                return Err(ParseError::TooLarge.into())
+            }
+            Ok(httparse::Status::Complete((len, hdrs))) => {
+                let mut headers = HeaderMap::with_capacity(hdrs.len());
+                for h in hdrs {
+                    let name = HeaderName::try_from(h.name).map_err(|_| ParseError::Header)?;
+                    let value = HeaderValue::try_from(h.value).map_err(|_| ParseError::Header)?;
+                    headers.append(name, value);
+                }
+                (len, headers)
+            }
+            Ok(httparse::Status::Partial) if self.buf.len() > MAX_HEADERS_SIZE => {
+                return Err(ParseError::TooLarge.into())
+            }
+            Ok(httparse::Status::Partial) => return Ok(None),
+            Err(err) => return Err(ParseError::from(err).into()),
+        };
+        self.buf.advance(len);
+        Ok(Some(headers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{Multipart, MAX_HEADERS, MAX_HEADERS_SIZE};
+    use actix_multipart::MultipartError;
+    use actix_web::error::ParseError;
+    use actix_web::http::header;
+    use bytes::Bytes;
+    use futures_util::{stream, StreamExt, TryStreamExt};
+
+    const BODY: &[u8] = b"preamble\r\n\
+        --abc\r\n\
+        Content-Disposition: form-data; name=\"field1\"\r\n\
+        \r\n\
+        value1\r\n\
+        --abc \r\n\
+        Content-Disposition: form-data; name=\"field2\"\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        line1\r\n--ab\r\nline2\r\n\
+        --abc--\r\n\
+        epilogue";
+
+    /// Parse the body, split into chunks of the given size.
+    async fn parse(body: &[u8], chunk_size: usize) -> Result<Vec<(String, Bytes)>, MultipartError> {
+        let chunks = body
+            .chunks(chunk_size)
+            .map(|c| Ok::<_, MultipartError>(Bytes::copy_from_slice(c)))
+            .collect::<Vec<_>>();
+        let mut multipart = Multipart::new("abc", stream::iter(chunks));
+        let mut parts = Vec::new();
+        while let Some(mut part) = multipart.try_next().await? {
+            let disposition = part
+                .headers
+                .get(header::CONTENT_DISPOSITION)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_owned();
+            let mut data = Vec::new();
+            while let Some(chunk) = part.body.next().await {
+                data.extend_from_slice(&chunk?);
+            }
+            parts.push((disposition, Bytes::from(data)));
+        }
+        Ok(parts)
+    }
+
+    #[actix_rt::test]
+    async fn test_parse() {
+        for chunk_size in 1..=BODY.len() {
+            let parts = parse(BODY, chunk_size).await.unwrap();
+            assert_eq!(parts.len(), 2);
+            assert_eq!(parts[0].0, "form-data; name=\"field1\"");
+            assert_eq!(parts[0].1, "value1");
+            assert_eq!(parts[1].0, "form-data; name=\"field2\"");
+            assert_eq!(parts[1].1, "line1\r\n--ab\r\nline2");
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_skip_unread_body() {
+        let chunks = vec![Ok::<_, MultipartError>(Bytes::from_static(BODY))];
+        let mut multipart = Multipart::new("abc", stream::iter(chunks));
+        let first = multipart.try_next().await.unwrap().unwrap();
+        let mut second = multipart.try_next().await.unwrap().unwrap();
+        assert_eq!(second.headers.len(), 2);
+        assert!(multipart.try_next().await.unwrap().is_none());
+
+        // Bodies can't be read once the stream has moved on
+        let mut first = first.body;
+        assert!(first.next().await.is_none());
+        assert!(second.body.next().await.is_none());
+    }
+
+    #[actix_rt::test]
+    async fn test_incomplete() {
+        let body = b"--abc\r\nContent-Disposition: form-data; name=\"field1\"\r\n\r\nvalue1";
+        assert!(matches!(
+            parse(body, 4).await,
+            Err(MultipartError::Incomplete)
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn test_headers_too_large() {
+        let padding = "a".repeat(MAX_HEADERS_SIZE);
+        let too_large = format!("--abc\r\nX-Padding: {padding}\r\n\r\nvalue\r\n--abc--\r\n");
+        let headers: String = (0..=MAX_HEADERS).map(|i| format!("X-{i}: a\r\n")).collect();
+        let too_many = format!("--abc\r\n{headers}\r\nvalue\r\n--abc--\r\n");
+        for body in [too_large, too_many] {
+            // Whether or not the header section is received in one go
+            for chunk_size in [1024, body.len()] {
+                assert!(matches!(
+                    parse(body.as_bytes(), chunk_size).await,
+                    Err(MultipartError::Parse(ParseError::TooLarge))
+                ));
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_empty() {
+        assert_eq!(parse(b"--abc--\r\n", 4).await.unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,497 @@
+//! Renders form errors as [RFC 7807](https://www.rfc-editor.org/rfc/rfc7807) problem details.
+#[cfg(feature = "archive")]
+use crate::archive::ArchiveError;
+#[cfg(feature = "cbor")]
+use crate::cbor::CborFieldError;
+#[cfg(feature = "csv")]
+use crate::csv::CsvFieldError;
+use crate::decompress::DecompressError;
+#[cfg(feature = "digest")]
+use crate::digest::IntegrityError;
+#[cfg(feature = "image")]
+use crate::image::ImageFieldError;
+use crate::json::JsonFieldError;
+#[cfg(feature = "msgpack")]
+use crate::msgpack::MsgPackFieldError;
+#[cfg(feature = "prost")]
+use crate::protobuf::ProtobufFieldError;
+use crate::scan::ScanError;
+use crate::sniff::SniffError;
+#[cfg(feature = "tempfile")]
+use crate::tempfile::TempfileError;
+use crate::text::TextError;
+#[cfg(feature = "toml")]
+use crate::toml::TomlFieldError;
+#[cfg(feature = "xml")]
+use crate::xml::XmlFieldError;
+#[cfg(feature = "yaml")]
+use crate::yaml::YamlFieldError;
+use crate::{Error, LimitKind, NotSent};
+use actix_web::error::InternalError;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// Every code given to an error, see [`ProblemDetails`].
+const CODES: [&str; 21] = [
+    "multipart",
+    "missing_field",
+    "duplicate_field",
+    "unsupported_field",
+    "limit_exceeded",
+    "content_type",
+    "utf8",
+    "decode",
+    "charset",
+    "control_character",
+    "deserialize",
+    "content_limit",
+    "io",
+    "integrity",
+    "content_encoding",
+    "image",
+    "archive",
+    "scan_rejected",
+    "scan_failed",
+    "field",
+    "multiple",
+];
+
+/// The codes given to errors that are caused by invalid form content, rather than by the transport
+/// or the server.
+const VALIDATION_CODES: [&str; 14] = [
+    "missing_field",
+    "duplicate_field",
+    "unsupported_field",
+    "content_type",
+    "deserialize",
+    "content_limit",
+    "utf8",
+    "decode",
+    "charset",
+    "control_character",
+    "scan_rejected",
+    "image",
+    "archive",
+    "multiple",
+];
+
+/// Renders an [`Error`] as an `application/problem+json` response.
+///
+/// Each error is given a stable `code` member, one of:
+///
+/// - `multipart`: The multipart stream could not be parsed.
+/// - `missing_field`, `duplicate_field`, `unsupported_field`: See [`Error`].
+/// - `limit_exceeded`: See [`Error::LimitExceeded`], the `limit` member is also set, and the
+///   `limit_kind` member is set to one of `total`, `memory`, `disk`, `field`, `parts`, `files`,
+///   `field_name_length`, `file_name_length`, `header_size`, `decompressed` or
+///   `compression_ratio`, see [`LimitKind`].
+/// - `content_type`: A field had an unexpected `Content-Type`, or its detected content type was
+///   rejected, see the [`sniff`](crate::sniff) module.
+/// - `utf8`: A text field was not valid UTF-8.
+/// - `decode`: A text field was not valid in its charset.
+/// - `charset`: A text field has an unsupported charset.
+/// - `control_character`: A text field contained a control character, see
+///   [`TextConfig::reject_control_chars`](crate::text::TextConfig::reject_control_chars).
+/// - `deserialize`: A field could not be deserialized.
+/// - `content_limit`: A limit on the content of a field was exceeded, such as the number of lines
+///   of a [`JsonLines`](crate::json::JsonLines) field or the number of rows of a
+///   [`Csv`](crate::csv::Csv) field.
+/// - `io`: A file I/O error occurred.
+/// - `integrity`: A file did not match the digest supplied by the client, see
+///   [`DigestConfig::verify`](crate::digest::DigestConfig::verify).
+/// - `content_encoding`: A part's `Content-Encoding` is not supported, see
+///   [`MultipartFormConfig::decompress`](crate::MultipartFormConfig::decompress).
+/// - `image`: An image field was not a valid image of an allowed format and size, see
+///   [`Image`](crate::image::Image).
+/// - `archive`: An archive field was not a valid archive, or exceeded the limits of its
+///   [`ArchiveConfig`](crate::archive::ArchiveConfig).
+/// - `scan_rejected`, `scan_failed`: A content scanner rejected a field, or failed to scan it,
+///   see the [`scan`](crate::scan) module.
+/// - `field`: Any other error returned by a field reader.
+/// - `multiple`: See [`Error::Multiple`], each error is included in the `errors` member.
+///
+/// When the error relates to a single field its name is included as the `field` member, and any
+/// error from the field reader is included as the `source` member.
+///
+/// Use with [`MultipartFormConfig::problem_details`](crate::MultipartFormConfig::problem_details).
+#[derive(Clone, Default)]
+pub struct ProblemDetails {
+    status_codes: HashMap<&'static str, StatusCode>,
+    type_base_uri: Option<String>,
+}
+
+impl ProblemDetails {
+    /// Set the status code used for a given error code. By default the status code of the error's
+    /// [`ResponseError`] implementation is used.
+    ///
+    /// The code must be one of those listed on [`ProblemDetails`], otherwise this panics.
+    pub fn status_code(mut self, code: &'static str, status: StatusCode) -> Self {
+        assert!(CODES.contains(&code), "unknown error code `{code}`");
+        self.status_codes.insert(code, status);
+        self
+    }
+
+    /// Set the status code used for all validation errors, i.e. `missing_field`,
+    /// `duplicate_field`, `unsupported_field`, `content_type`, `utf8`, `decode`, `charset`,
+    /// `control_character`, `deserialize`, `content_limit`, `scan_rejected`, `image`, `archive`
+    /// and `multiple`. For example you may want to use `422 Unprocessable Entity`.
+    pub fn validation_status_code(mut self, status: StatusCode) -> Self {
+        for code in VALIDATION_CODES {
+            self.status_codes.insert(code, status);
+        }
+        self
+    }
+
+    /// Set a base URI used to build the `type` member, by appending the error code. By default
+    /// the `type` is `about:blank`.
+    pub fn type_base_uri(mut self, uri: impl Into<String>) -> Self {
+        self.type_base_uri = Some(uri.into());
+        self
+    }
+
+    /// Render the error as an `application/problem+json` response.
+    pub fn render(&self, err: &Error) -> HttpResponse {
+        let code = error_code(err);
+        let status = self
+            .status_codes
+            .get(code)
+            .copied()
+            .unwrap_or_else(|| err.status_code());
+
+        let mut body = Map::new();
+        let problem_type = match &self.type_base_uri {
+            Some(base) => format!("{base}{code}"),
+            None => "about:blank".to_owned(),
+        };
+        body.insert("type".to_owned(), problem_type.into());
+        body.insert(
+            "title".to_owned(),
+            status.canonical_reason().unwrap_or_default().into(),
+        );
+        body.insert("status".to_owned(), status.as_u16().into());
+        body.extend(error_members(err));
+
+        HttpResponse::build(status)
+            .content_type("application/problem+json")
+            .body(Value::Object(body).to_string())
+    }
+
+    pub(crate) fn map_error(&self, err: Error) -> actix_web::Error {
+        let response = self.render(&err);
+        InternalError::from_response(err, response).into()
+    }
+}
+
+/// The members describing an error, excluding those that describe the response.
+fn error_members(err: &Error) -> Map<String, Value> {
+    let mut body = Map::new();
+    body.insert("detail".to_owned(), err.to_string().into());
+    body.insert("code".to_owned(), error_code(err).into());
+
+    match err {
+        Error::Field { field_name, source } => {
+            body.insert("field".to_owned(), field_name.as_str().into());
+            body.insert(
+                "source".to_owned(),
+                json!({
+                    "code": field_error_code(source).unwrap_or("field"),
+                    "detail": source.to_string(),
+                }),
+            );
+        }
+        Error::DuplicateField(field_name)
+        | Error::MissingField(field_name)
+        | Error::UnsupportedField(field_name) => {
+            body.insert("field".to_owned(), field_name.as_str().into());
+        }
+        Error::LimitExceeded {
+            kind,
+            field_name,
+            limit,
+        } => {
+            if let Some(field_name) = field_name {
+                body.insert("field".to_owned(), field_name.as_str().into());
+            }
+            body.insert("limit_kind".to_owned(), limit_kind_code(kind).into());
+            body.insert("limit".to_owned(), (*limit).into());
+        }
+        Error::Multiple(errors) => {
+            let errors = errors
+                .iter()
+                .map(|e| Value::Object(error_members(e)))
+                .collect::<Vec<_>>();
+            body.insert("errors".to_owned(), errors.into());
+        }
+        Error::Multipart(_) => {}
+    }
+    body
+}
+
+pub(crate) fn error_code(err: &Error) -> &'static str {
+    match err {
+        Error::Multipart(_) => "multipart",
+        Error::Field { source, .. } => field_error_code(source).unwrap_or("field"),
+        Error::DuplicateField(_) => "duplicate_field",
+        Error::MissingField(_) => "missing_field",
+        Error::UnsupportedField(_) => "unsupported_field",
+        Error::LimitExceeded { .. } => "limit_exceeded",
+        Error::Multiple(_) => "multiple",
+    }
+}
+
+/// Get the code for a kind of limit, which unlike its `Display` implementation is stable.
+fn limit_kind_code(kind: &LimitKind) -> &'static str {
+    match kind {
+        LimitKind::Total => "total",
+        LimitKind::Memory => "memory",
+        LimitKind::Disk => "disk",
+        LimitKind::Field => "field",
+        LimitKind::Parts => "parts",
+        LimitKind::Files => "files",
+        LimitKind::FieldNameLength => "field_name_length",
+        LimitKind::FileNameLength => "file_name_length",
+        LimitKind::HeaderSize => "header_size",
+        LimitKind::Decompressed => "decompressed",
+        LimitKind::CompressionRatio => "compression_ratio",
+    }
+}
+
+/// Get the code for an error produced by one of the built-in field readers.
+fn field_error_code(source: &actix_web::Error) -> Option<&'static str> {
+    if source.as_error::<NotSent>().is_some() {
+        return Some("missing_field");
+    }
+    if let Some(err) = source.as_error::<TextError>() {
+        return Some(match err {
+            TextError::Utf8Error(_) => "utf8",
+            TextError::Deserialize(_) => "deserialize",
+            TextError::ContentType => "content_type",
+            TextError::Decode(_) => "decode",
+            TextError::UnsupportedCharset(_) => "charset",
+            TextError::Parse(_) => "deserialize",
+            TextError::ControlCharacter => "control_character",
+            TextError::TooManyItems(_) | TextError::TooManyChars(_) => "content_limit",
+        });
+    }
+    if let Some(err) = source.as_error::<JsonFieldError>() {
+        return Some(match err {
+            JsonFieldError::Deserialize(_)
+            | JsonFieldError::Line(..)
+            | JsonFieldError::DuplicateKey(_) => "deserialize",
+            JsonFieldError::ContentType => "content_type",
+            JsonFieldError::TooManyItems(_)
+            | JsonFieldError::LineTooLong(_)
+            | JsonFieldError::TooDeep(_) => "content_limit",
+        });
+    }
+    #[cfg(feature = "msgpack")]
+    if let Some(err) = source.as_error::<MsgPackFieldError>() {
+        return Some(match err {
+            MsgPackFieldError::Deserialize(_) | MsgPackFieldError::TrailingData => "deserialize",
+            MsgPackFieldError::ContentType => "content_type",
+        });
+    }
+    #[cfg(feature = "cbor")]
+    if let Some(err) = source.as_error::<CborFieldError>() {
+        return Some(match err {
+            CborFieldError::Deserialize(_) | CborFieldError::TrailingData => "deserialize",
+            CborFieldError::ContentType => "content_type",
+        });
+    }
+    #[cfg(feature = "prost")]
+    if let Some(err) = source.as_error::<ProtobufFieldError>() {
+        return Some(match err {
+            ProtobufFieldError::Decode(_) | ProtobufFieldError::Base64(_) => "deserialize",
+            ProtobufFieldError::ContentType => "content_type",
+        });
+    }
+    #[cfg(feature = "yaml")]
+    if let Some(err) = source.as_error::<YamlFieldError>() {
+        return Some(match err {
+            YamlFieldError::Deserialize(_) => "deserialize",
+            YamlFieldError::ContentType => "content_type",
+        });
+    }
+    #[cfg(feature = "toml")]
+    if let Some(err) = source.as_error::<TomlFieldError>() {
+        return Some(match err {
+            TomlFieldError::Deserialize(_) => "deserialize",
+            TomlFieldError::Utf8Error(_) => "utf8",
+            TomlFieldError::ContentType => "content_type",
+        });
+    }
+    #[cfg(feature = "xml")]
+    if let Some(err) = source.as_error::<XmlFieldError>() {
+        return Some(match err {
+            XmlFieldError::Deserialize(_) | XmlFieldError::Doctype => "deserialize",
+            XmlFieldError::Utf8Error(_) => "utf8",
+            XmlFieldError::ContentType => "content_type",
+        });
+    }
+    #[cfg(feature = "csv")]
+    if let Some(err) = source.as_error::<CsvFieldError>() {
+        return Some(match err {
+            CsvFieldError::Parse { .. } => "deserialize",
+            CsvFieldError::TooManyRows(_)
+            | CsvFieldError::RowTooLong { .. }
+            | CsvFieldError::TooManyFields { .. } => "content_limit",
+        });
+    }
+    if source.as_error::<SniffError>().is_some() {
+        return Some("content_type");
+    }
+    #[cfg(feature = "digest")]
+    if source.as_error::<IntegrityError>().is_some() {
+        return Some("integrity");
+    }
+    if let Some(err) = source.as_error::<DecompressError>() {
+        return Some(match err {
+            DecompressError::UnsupportedEncoding(_) => "content_encoding",
+        });
+    }
+    #[cfg(feature = "image")]
+    if let Some(err) = source.as_error::<ImageFieldError>() {
+        // A failure to re-encode is not a problem with the image
+        if !matches!(err, ImageFieldError::Encode(_)) {
+            return Some("image");
+        }
+    }
+    #[cfg(feature = "archive")]
+    if let Some(err) = source.as_error::<ArchiveError>() {
+        return Some(match err {
+            ArchiveError::Io(_) => "io",
+            _ => "archive",
+        });
+    }
+    if let Some(err) = source.as_error::<ScanError>() {
+        return Some(match err {
+            ScanError::Rejected(_) => "scan_rejected",
+            ScanError::Failed(_) => "scan_failed",
+        });
+    }
+    #[cfg(feature = "tempfile")]
+    if let Some(err) = source.as_error::<TempfileError>() {
+        return Some(match err {
+            TempfileError::FileIo(_) => "io",
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::Json;
+    use crate::problem::ProblemDetails;
+    use crate::tests::send_form;
+    use crate::text::Text;
+    use crate::{Error, LimitKind, MultipartForm, MultipartFormConfig, NotSent};
+    use actix_multipart_rfc7578::client::multipart;
+    use actix_web::http::{header, StatusCode};
+    use actix_web::{web, App, HttpResponse, Responder, ResponseError};
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    #[derive(MultipartForm)]
+    struct ProblemForm {
+        _name: Text<String>,
+        _json: Option<Json<HashMap<String, String>>>,
+    }
+
+    async fn test_problem_route(_: MultipartForm<ProblemForm>) -> impl Responder {
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_problem_details() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_problem_route))
+                .app_data(
+                    MultipartFormConfig::default().problem_details(
+                        ProblemDetails::default()
+                            .validation_status_code(StatusCode::UNPROCESSABLE_ENTITY),
+                    ),
+                )
+        });
+
+        // Missing field
+        let mut form = multipart::Form::default();
+        form.add_text("_other", "value");
+        let mut response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "type": "about:blank",
+                "title": "Unprocessable Entity",
+                "status": 422,
+                "detail": "Field with name `_name` is required",
+                "code": "missing_field",
+                "field": "_name",
+            })
+        );
+
+        // Field reader error
+        let mut form = multipart::Form::default();
+        form.add_text("_name", "value");
+        let bytes = Cursor::new("{}");
+        form.add_reader_file_with_mime("_json", bytes, "", mime::TEXT_PLAIN);
+        let mut response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["code"], "content_type");
+        assert_eq!(body["field"], "_json");
+        assert_eq!(body["source"]["code"], "content_type");
+    }
+
+    #[actix_rt::test]
+    async fn test_problem_not_sent() {
+        // A field treated as not sent is reported in the same way as a missing field
+        let err = NotSent::error("name".to_owned());
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+        let response = ProblemDetails::default().render(&err);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "missing_field");
+        assert_eq!(body["field"], "name");
+        assert_eq!(body["source"]["code"], "missing_field");
+    }
+
+    #[actix_rt::test]
+    async fn test_problem_limit_kind() {
+        let err = Error::LimitExceeded {
+            kind: LimitKind::CompressionRatio,
+            field_name: Some("file".to_owned()),
+            limit: 100,
+        };
+        let response = ProblemDetails::default()
+            .status_code("limit_exceeded", StatusCode::BAD_REQUEST)
+            .render(&err);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "limit_exceeded");
+        assert_eq!(body["limit_kind"], "compression_ratio");
+        assert_eq!(body["limit"], 100);
+        assert_eq!(body["field"], "file");
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown error code")]
+    fn test_problem_unknown_code() {
+        let _ = ProblemDetails::default().status_code("missing", StatusCode::BAD_REQUEST);
+    }
+}
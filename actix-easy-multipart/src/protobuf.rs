@@ -0,0 +1,258 @@
+//! Decodes a field as a Protocol Buffers message, enabled using the `prost` feature.
+use crate::bytes::Bytes;
+use crate::{is_format, is_valid_content_type, Field, FieldReader, Limits};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, ResponseError};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use derive_more::{Deref, DerefMut, Display, Error};
+use futures_core::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use prost::Message;
+use std::sync::Arc;
+
+/// Decode a [Protocol Buffers](https://protobuf.dev/) message, using [`prost`].
+///
+/// The field contains the binary encoding of the message, or in base64 mode the base64 encoding of
+/// it, see [`ProtobufConfig::base64`].
+#[derive(Debug, Deref, DerefMut)]
+pub struct Protobuf<T: Message + Default>(pub T);
+
+impl<T: Message + Default> Protobuf<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<'t, T: Message + Default + 'static> FieldReader<'t> for Protobuf<T> {
+    type Future = LocalBoxFuture<'t, Result<Self, crate::Error>>;
+
+    fn read_field(req: &'t HttpRequest, mut field: Field, limits: &'t mut Limits) -> Self::Future {
+        async move {
+            let config = ProtobufConfig::from_req(req);
+            let field_name = field.name().to_owned();
+
+            // Text fields don't usually have a content type
+            let text_field = config.base64 && field.content_type().is_none();
+            let validate = config.validate_content_type && !text_field;
+            if !is_valid_content_type(&field, validate, |mime| {
+                if config.base64 {
+                    mime.essence_str() == mime::TEXT_PLAIN.essence_str()
+                } else {
+                    is_format(
+                        mime,
+                        &["x-protobuf", "protobuf", "vnd.google.protobuf"],
+                        "protobuf",
+                    )
+                }
+            }) {
+                return Err(crate::Error::Field {
+                    field_name,
+                    source: config.map_error(req, ProtobufFieldError::ContentType),
+                });
+            }
+
+            let bytes = Bytes::read_unscanned(req, &mut field, limits).await?;
+            let field_error = |err: ProtobufFieldError| crate::Error::Field {
+                field_name: field_name.clone(),
+                source: config.map_error(req, err),
+            };
+
+            let data = if config.base64 {
+                let text: Vec<u8> = bytes
+                    .data
+                    .iter()
+                    .copied()
+                    .filter(|b| !b.is_ascii_whitespace())
+                    .collect();
+                limits.check_memory(base64::decoded_len_estimate(text.len()))?;
+                BASE64
+                    .decode(text)
+                    .map_err(|e| field_error(ProtobufFieldError::Base64(e)))?
+                    .into()
+            } else {
+                bytes.data
+            };
+            Ok(Protobuf(
+                T::decode(data).map_err(|e| field_error(ProtobufFieldError::Decode(e)))?,
+            ))
+        }
+        .boxed_local()
+    }
+}
+
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum ProtobufFieldError {
+    /// Decode error
+    #[display(fmt = "Protobuf decode error: {}", _0)]
+    Decode(prost::DecodeError),
+
+    /// Base64 decode error
+    #[display(fmt = "Base64 decode error: {}", _0)]
+    Base64(base64::DecodeError),
+
+    /// Content type error
+    #[display(fmt = "Content type error")]
+    ContentType,
+}
+
+impl ResponseError for ProtobufFieldError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// Configuration for the [`Protobuf`] field reader.
+#[derive(Clone)]
+pub struct ProtobufConfig {
+    err_handler:
+        Option<Arc<dyn Fn(ProtobufFieldError, &HttpRequest) -> actix_web::Error + Send + Sync>>,
+    validate_content_type: bool,
+    base64: bool,
+}
+
+const DEFAULT_CONFIG: ProtobufConfig = ProtobufConfig {
+    err_handler: None,
+    validate_content_type: true,
+    base64: false,
+};
+
+impl ProtobufConfig {
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(ProtobufFieldError, &HttpRequest) -> actix_web::Error + Send + Sync + 'static,
+    {
+        self.err_handler = Some(Arc::new(f));
+        self
+    }
+
+    /// Extract payload config from app data. Check both `T` and `Data<T>`, in that order, and fall
+    /// back to the default payload config.
+    fn from_req(req: &HttpRequest) -> &Self {
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<web::Data<Self>>().map(|d| d.as_ref()))
+            .unwrap_or(&DEFAULT_CONFIG)
+    }
+
+    fn map_error(&self, req: &HttpRequest, err: ProtobufFieldError) -> actix_web::Error {
+        if let Some(err_handler) = self.err_handler.as_ref() {
+            (*err_handler)(err, req)
+        } else {
+            err.into()
+        }
+    }
+
+    /// Sets whether or not the field must have a valid `Content-Type` header to be parsed, which
+    /// has one of the `x-protobuf`, `protobuf` or `vnd.google.protobuf` subtypes (e.g.
+    /// `application/x-protobuf`) or the `+protobuf` suffix. In base64 mode the field must instead
+    /// be a text field, with no content type or `text/plain`.
+    pub fn validate_content_type(mut self, validate_content_type: bool) -> Self {
+        self.validate_content_type = validate_content_type;
+        self
+    }
+
+    /// Sets whether the message is base64 encoded, by default false. This allows the message to be
+    /// sent as a text field, for example from a `<textarea>`. Whitespace is ignored. The decoded
+    /// message must also fit within the form's memory limit.
+    pub fn base64(mut self, base64: bool) -> Self {
+        self.base64 = base64;
+        self
+    }
+}
+
+impl Default for ProtobufConfig {
+    fn default() -> Self {
+        DEFAULT_CONFIG
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::protobuf::{Protobuf, ProtobufConfig};
+    use crate::tests::send_form;
+    use crate::{MultipartForm, MultipartFormConfig};
+    use actix_multipart_rfc7578::client::multipart;
+    use actix_web::http::StatusCode;
+    use actix_web::{web, App, HttpResponse, Responder};
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use base64::Engine;
+    use prost::Message;
+    use std::io::Cursor;
+
+    #[derive(Clone, PartialEq, Message)]
+    struct Metadata {
+        #[prost(string, tag = "1")]
+        device: String,
+        #[prost(uint32, tag = "2")]
+        temperature: u32,
+    }
+
+    fn metadata() -> Metadata {
+        Metadata {
+            device: "sensor-1".to_owned(),
+            temperature: 21,
+        }
+    }
+
+    #[derive(MultipartForm)]
+    struct ProtobufForm {
+        metadata: Protobuf<Metadata>,
+    }
+
+    async fn test_protobuf_route(form: MultipartForm<ProtobufForm>) -> impl Responder {
+        assert_eq!(*form.metadata, metadata());
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_protobuf() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_protobuf_route))
+                .app_data(ProtobufConfig::default())
+        });
+        let form = |data: Vec<u8>, mime: &str| {
+            let mut form = multipart::Form::default();
+            let mime = mime.parse().unwrap();
+            form.add_reader_file_with_mime("metadata", Cursor::new(data), "", mime);
+            form
+        };
+
+        let data = metadata().encode_to_vec();
+        let response = send_form(&srv, form(data.clone(), "application/x-protobuf"), "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Deny because wrong content type
+        let response = send_form(&srv, form(data, "application/json"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Deny because invalid data
+        let response = send_form(&srv, form(vec![0xff], "application/x-protobuf"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_protobuf_base64() {
+        let srv = |memory_limit: usize| {
+            actix_test::start(move || {
+                App::new()
+                    .route("/", web::post().to(test_protobuf_route))
+                    .app_data(ProtobufConfig::default().base64(true))
+                    .app_data(MultipartFormConfig::default().memory_limit(memory_limit))
+            })
+        };
+        let form = || {
+            let mut form = multipart::Form::default();
+            form.add_text("metadata", BASE64.encode(metadata().encode_to_vec()));
+            form
+        };
+
+        let response = send_form(&srv(40), form(), "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Deny because the decoded message would exceed the memory limit
+        let response = send_form(&srv(20), form(), "/").await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,276 @@
+//! Extractors for `multipart/mixed` and `multipart/related` bodies, see
+//! [RFC 2046 §5.1.3](https://www.rfc-editor.org/rfc/rfc2046#section-5.1.3) and
+//! [RFC 2387](https://www.rfc-editor.org/rfc/rfc2387).
+//!
+//! The parts of these bodies are not required to have a `Content-Disposition` header, so each part
+//! is given a field name as follows:
+//!
+//! 1. A field with a matching `#[multipart(root)]`, `#[multipart(content_id = "")]` or
+//!    `#[multipart(position = N)]` attribute.
+//! 2. The `name` parameter of the part's `Content-Disposition` header, if present.
+//! 3. The part's `Content-ID` header, without the enclosing angle brackets.
+//!
+//! Otherwise the part is treated as an unknown field with an empty name.
+//!
+//! ```
+//! # use actix_easy_multipart::bytes::Bytes;
+//! # use actix_easy_multipart::json::Json;
+//! # use actix_easy_multipart::related::MultipartRelated;
+//! # use actix_easy_multipart::MultipartForm;
+//! # use actix_web::Responder;
+//! #[derive(MultipartForm)]
+//! struct Document {
+//!     #[multipart(root)]
+//!     metadata: Json<serde_json::Value>,
+//!     #[multipart(content_id = "signature@example.com")]
+//!     signature: Option<Bytes>,
+//!     attachments: Vec<Bytes>,
+//! }
+//!
+//! async fn route(form: MultipartRelated<Document>) -> impl Responder {
+//!     format!("Received {} attachments", form.attachments.len())
+//! }
+//! ```
+use crate::field::{part_content_disposition, Field};
+use crate::parser::Part;
+use crate::{extract, Body, MultipartFormTrait};
+use actix_web::dev::Payload;
+use actix_web::http::header::{HeaderMap, HeaderName};
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use derive_more::{Deref, DerefMut};
+use futures_core::future::LocalBoxFuture;
+use futures_util::FutureExt;
+
+/// Describes a part of a `multipart/mixed` or `multipart/related` body, used to match the part to
+/// a field of the form.
+#[derive(Debug)]
+pub struct PartInfo<'a> {
+    /// The zero-based position of the part within the body.
+    pub index: usize,
+    /// The part's `Content-ID` header, without the enclosing angle brackets.
+    pub content_id: Option<&'a str>,
+    /// Whether this is the root part of a `multipart/related` body, that is the part identified
+    /// by the `start` parameter, or else the first part.
+    pub root: bool,
+}
+
+/// Typed `multipart/mixed` extractor.
+///
+/// This works the same as [`MultipartForm`](struct@crate::MultipartForm), see the
+/// [module documentation](self) for how parts are matched to fields.
+#[derive(Deref, DerefMut)]
+pub struct MultipartMixed<T: MultipartFormTrait>(pub T);
+
+impl<T: MultipartFormTrait> MultipartMixed<T> {
+    /// Unwrap into inner `T` value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> FromRequest for MultipartMixed<T>
+where
+    T: MultipartFormTrait,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let payload = payload.take();
+        async move { Ok(MultipartMixed(extract_parts(req, payload).await?)) }.boxed_local()
+    }
+}
+
+/// Typed `multipart/related` extractor.
+///
+/// This works the same as [`MultipartForm`](struct@crate::MultipartForm), see the
+/// [module documentation](self) for how parts are matched to fields.
+#[derive(Deref, DerefMut)]
+pub struct MultipartRelated<T: MultipartFormTrait>(pub T);
+
+impl<T: MultipartFormTrait> MultipartRelated<T> {
+    /// Unwrap into inner `T` value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> FromRequest for MultipartRelated<T>
+where
+    T: MultipartFormTrait,
+{
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    #[inline]
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        let payload = payload.take();
+        async move { Ok(MultipartRelated(extract_parts(req, payload).await?)) }.boxed_local()
+    }
+}
+
+async fn extract_parts<T>(req: HttpRequest, payload: Payload) -> Result<T, actix_web::Error>
+where
+    T: MultipartFormTrait,
+{
+    let start = req.mime_type().ok().flatten().and_then(|mime| {
+        mime.get_param("start")
+            .map(|s| strip_angle_brackets(s.as_str()).to_owned())
+    });
+    let mut index = 0;
+
+    let to_field = move |part: Part| {
+        let content_id = content_id(&part.headers);
+        let info = PartInfo {
+            index,
+            content_id,
+            root: match &start {
+                Some(start) => content_id == Some(start.as_str()),
+                None => index == 0,
+            },
+        };
+        index += 1;
+
+        let name = T::match_part(&info)
+            .map(str::to_owned)
+            .or_else(|| {
+                part_content_disposition(&part.headers)
+                    .and_then(|cd| cd.get_name().map(str::to_owned))
+            })
+            .or_else(|| content_id.map(str::to_owned))
+            .unwrap_or_default();
+        Ok(Field::from_part(name, part))
+    };
+    extract(req, payload, Body::Multipart(to_field)).await
+}
+
+/// Get the `Content-ID` header of a part.
+fn content_id(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(HeaderName::from_static("content-id"))
+        .and_then(|id| id.to_str().ok())
+        .map(strip_angle_brackets)
+}
+
+fn strip_angle_brackets(id: &str) -> &str {
+    let id = id.trim();
+    id.strip_prefix('<')
+        .and_then(|id| id.strip_suffix('>'))
+        .unwrap_or(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bytes::Bytes;
+    use crate::related::{MultipartMixed, MultipartRelated};
+    use crate::text::Text;
+    use crate::MultipartForm;
+    use actix_web::http::StatusCode;
+    use actix_web::{web, App, HttpResponse, Responder};
+    use awc::Client;
+
+    #[derive(MultipartForm)]
+    struct TestRelated {
+        #[multipart(root)]
+        root: Text<String>,
+        #[multipart(content_id = "<image@example.com>")]
+        image: Bytes,
+        #[multipart(position = 2)]
+        third: Text<String>,
+        named: Option<Text<String>>,
+    }
+
+    async fn test_related_route(form: MultipartRelated<TestRelated>) -> impl Responder {
+        assert_eq!(&*form.root, "root");
+        assert_eq!(&form.image.data[..], b"image");
+        assert_eq!(&*form.third, "third");
+        assert_eq!(form.named.as_deref().map(String::as_str), Some("named"));
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_related() {
+        let srv = actix_test::start(|| App::new().route("/", web::post().to(test_related_route)));
+
+        // The root part is identified by the start parameter, rather than being the first part
+        let body = "--AaB03x\r\n\
+            Content-ID: <image@example.com>\r\n\
+            Content-Type: image/png\r\n\
+            \r\n\
+            image\r\n\
+            --AaB03x\r\n\
+            Content-ID: <root@example.com>\r\n\
+            \r\n\
+            root\r\n\
+            --AaB03x\r\n\
+            \r\n\
+            third\r\n\
+            --AaB03x\r\n\
+            Content-Disposition: attachment; name=\"named\"\r\n\
+            \r\n\
+            named\r\n\
+            --AaB03x--\r\n";
+        let response = Client::default()
+            .post(srv.url("/"))
+            .content_type("multipart/related; boundary=AaB03x; start=\"<root@example.com>\"")
+            .send_body(body)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[derive(MultipartForm)]
+    #[multipart(deny_unknown_fields)]
+    struct TestMixed {
+        #[multipart(position = 0)]
+        first: Text<String>,
+        #[multipart(rename = "part@example.com")]
+        by_id: Text<String>,
+    }
+
+    async fn test_mixed_route(form: MultipartMixed<TestMixed>) -> impl Responder {
+        assert_eq!(&*form.first, "first");
+        assert_eq!(&*form.by_id, "second");
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_mixed() {
+        let srv = actix_test::start(|| App::new().route("/", web::post().to(test_mixed_route)));
+
+        let body = "--AaB03x\r\n\
+            \r\n\
+            first\r\n\
+            --AaB03x\r\n\
+            Content-ID: <part@example.com>\r\n\
+            \r\n\
+            second\r\n\
+            --AaB03x--\r\n";
+        let response = Client::default()
+            .post(srv.url("/"))
+            .content_type("multipart/mixed; boundary=AaB03x")
+            .send_body(body)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // A part which can't be matched to a field
+        let body = "--AaB03x\r\n\
+            \r\n\
+            first\r\n\
+            --AaB03x\r\n\
+            \r\n\
+            unknown\r\n\
+            --AaB03x--\r\n";
+        let response = Client::default()
+            .post(srv.url("/"))
+            .content_type("multipart/mixed; boundary=AaB03x")
+            .send_body(body)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,713 @@
+//! Scanning of uploaded data before it reaches the handler, for example for malware.
+//!
+//! Scanners implement the [`ContentScanner`] trait and are registered using a [`ScanConfig`]. The
+//! [`Bytes`](crate::bytes::Bytes) and [`Tempfile`](crate::tempfile::Tempfile) field readers run
+//! each scanner once the field has been read, and fail with a [`ScanError`] if any scanner rejects
+//! the data. Field readers that parse the data, such as [`Text`](crate::text::Text) and
+//! [`Json`](crate::json::Json), do not run the scanners.
+//!
+//! ```
+//! # use actix_easy_multipart::scan::{ActiveContentScanner, ScanConfig};
+//! # use actix_web::App;
+//! let app = App::new().app_data(ScanConfig::default().scanner(ActiveContentScanner));
+//! ```
+//!
+//! Two scanners are included:
+//!
+//! - [`ActiveContentScanner`] rejects SVG and HTML uploads containing scripts.
+//! - `ClamdScanner` uses a [ClamAV](https://www.clamav.net/) daemon, enabled using the `clamd`
+//!   feature (Unix only).
+use crate::Field;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, ResponseError};
+use derive_more::{Display, Error};
+use futures_core::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use mime::Mime;
+use std::borrow::Cow;
+use std::io;
+#[cfg(feature = "tempfile")]
+use std::path::Path;
+use std::sync::Arc;
+
+/// The outcome of scanning an upload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// The upload may be passed to the handler.
+    Clean,
+    /// The upload must be rejected, for the given reason.
+    Reject(String),
+}
+
+/// The data of an upload.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum Content<'a> {
+    /// Data held in memory.
+    Bytes(&'a [u8]),
+    /// Data written to a file on disk.
+    #[cfg(feature = "tempfile")]
+    File(&'a Path),
+}
+
+impl<'a> Content<'a> {
+    /// Read the whole of the data into memory.
+    ///
+    /// A file is read in full regardless of the form's memory limit, so prefer
+    /// [`Content::read_chunks`] for files that may be large.
+    pub async fn read(&self) -> io::Result<Cow<'a, [u8]>> {
+        match *self {
+            Content::Bytes(data) => Ok(Cow::Borrowed(data)),
+            #[cfg(feature = "tempfile")]
+            Content::File(path) => tokio::fs::read(path).await.map(Cow::Owned),
+        }
+    }
+
+    /// Read the data in chunks of at most `chunk_size` bytes, calling `f` with each chunk until it
+    /// returns false. Returns false if `f` did.
+    pub async fn read_chunks<F>(&self, chunk_size: usize, mut f: F) -> io::Result<bool>
+    where
+        F: FnMut(&[u8]) -> bool,
+    {
+        match *self {
+            Content::Bytes(data) => Ok(data.chunks(chunk_size.max(1)).all(&mut f)),
+            #[cfg(feature = "tempfile")]
+            Content::File(path) => {
+                use tokio::io::AsyncReadExt;
+                let mut file = tokio::fs::File::open(path).await?;
+                let mut buf = vec![0; chunk_size.max(1)];
+                loop {
+                    let len = file.read(&mut buf).await?;
+                    if len == 0 {
+                        return Ok(true);
+                    }
+                    if !f(&buf[..len]) {
+                        return Ok(false);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// An upload to be scanned.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct Upload<'a> {
+    /// The name of the field.
+    pub field_name: &'a str,
+    /// The filename given by the client, which is untrusted.
+    pub file_name: Option<&'a str>,
+    /// The declared `Content-Type` of the field, which is untrusted.
+    pub content_type: Option<&'a Mime>,
+    /// The content type detected from the data, see [`Field::detected_content_type`].
+    pub detected_content_type: Option<&'a Mime>,
+    /// The data.
+    pub content: Content<'a>,
+}
+
+/// Scans uploads before they are passed to the handler.
+pub trait ContentScanner {
+    /// Scan an upload. An error means that the upload could not be scanned, and it will be
+    /// rejected with [`ScanError::Failed`].
+    fn scan<'a>(&'a self, upload: Upload<'a>) -> LocalBoxFuture<'a, io::Result<Verdict>>;
+}
+
+/// A content scanning error.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum ScanError {
+    /// A scanner rejected the upload
+    #[display(fmt = "Upload rejected: {}", _0)]
+    Rejected(#[error(not(source))] String),
+    /// A scanner failed to scan the upload
+    #[display(fmt = "Upload could not be scanned: {}", _0)]
+    Failed(io::Error),
+}
+
+impl ResponseError for ScanError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ScanError::Rejected(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ScanError::Failed(_) => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+/// Configuration of the scanners run on each upload, see the [module documentation](self).
+#[derive(Clone, Default)]
+pub struct ScanConfig {
+    scanners: Vec<Arc<dyn ContentScanner>>,
+}
+
+impl ScanConfig {
+    /// Add a scanner, the scanners are run in the order they are added.
+    pub fn scanner<S: ContentScanner + 'static>(mut self, scanner: S) -> Self {
+        self.scanners.push(Arc::new(scanner));
+        self
+    }
+
+    /// Extract scan config from app data. Check both `T` and `Data<T>`, in that order.
+    fn from_req(req: &HttpRequest) -> Option<&Self> {
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<web::Data<Self>>().map(|d| d.as_ref()))
+    }
+}
+
+/// Run the configured scanners on a field's data, once it has been read.
+pub(crate) async fn scan_field(
+    req: &HttpRequest,
+    field: &Field,
+    content: Content<'_>,
+) -> Result<(), crate::Error> {
+    let config = match ScanConfig::from_req(req) {
+        Some(config) => config,
+        None => return Ok(()),
+    };
+    let file_name = field.file_name();
+    let upload = Upload {
+        field_name: field.name(),
+        file_name: file_name.as_deref(),
+        content_type: field.content_type(),
+        detected_content_type: field.detected_content_type(),
+        content,
+    };
+    for scanner in &config.scanners {
+        let err = match scanner.scan(upload).await {
+            Ok(Verdict::Clean) => continue,
+            Ok(Verdict::Reject(reason)) => ScanError::Rejected(reason),
+            Err(err) => ScanError::Failed(err),
+        };
+        return Err(crate::Error::Field {
+            field_name: field.name().to_owned(),
+            source: err.into(),
+        });
+    }
+    Ok(())
+}
+
+/// Rejects SVG and HTML uploads that contain active content, i.e. scripts, event handler
+/// attributes, `javascript:` URLs or embedded documents, which could be run in the browser of a
+/// user that later views the upload.
+///
+/// An upload is checked if its declared or detected content type, or its filename extension, is
+/// SVG, HTML or XHTML, or if the data starts like an SVG or HTML document. The check is a simple
+/// search for the markers of active content, so may reject some harmless documents. Character
+/// references (e.g. `&#115;`) are decoded before searching for `javascript:` URLs.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ActiveContentScanner;
+
+/// Markers of active content, which are matched case insensitively.
+const ACTIVE_CONTENT: [&str; 7] = [
+    "<script",
+    "<iframe",
+    "<embed",
+    "<object",
+    "<foreignobject",
+    "<handler",
+    "<meta http-equiv",
+];
+
+/// The marker of a script URL, which is matched case insensitively after decoding any character
+/// references, and ignoring tabs and newlines as a browser does.
+const SCRIPT_URL: &str = "javascript:";
+
+/// The longest character reference that is decoded, not including leading zeros.
+const MAX_REFERENCE_LEN: usize = 32;
+
+/// The number of bytes at the start of an upload used to detect SVG and HTML documents.
+const MARKUP_DETECT_LEN: usize = 1024;
+
+/// The size of the chunks the data is searched in.
+const SEARCH_CHUNK_SIZE: usize = 65_536; // 64 KiB
+
+impl ActiveContentScanner {
+    fn is_markup(upload: &Upload, start: &[u8]) -> bool {
+        const TYPES: [&str; 3] = ["image/svg+xml", "text/html", "application/xhtml+xml"];
+        const EXTENSIONS: [&str; 4] = [".svg", ".html", ".htm", ".xhtml"];
+
+        let content_types = [upload.content_type, upload.detected_content_type];
+        if content_types
+            .iter()
+            .flatten()
+            .any(|ct| TYPES.contains(&ct.essence_str()))
+        {
+            return true;
+        }
+        if let Some(file_name) = upload.file_name {
+            let file_name = file_name.to_ascii_lowercase();
+            if EXTENSIONS.iter().any(|ext| file_name.ends_with(ext)) {
+                return true;
+            }
+        }
+        let start = String::from_utf8_lossy(start).to_ascii_lowercase();
+        let start = start.trim_start_matches('\u{feff}').trim_start();
+        start.starts_with("<svg")
+            || start.starts_with("<html")
+            || start.starts_with("<!doctype html")
+            || (start.starts_with("<?xml") && (start.contains("<svg") || start.contains("<html")))
+    }
+
+    /// Find the first marker of active content, if any.
+    #[cfg(test)]
+    fn find_active_content(data: &[u8]) -> Option<String> {
+        let mut search = ActiveContentSearch::default();
+        search.push(data);
+        search.found
+    }
+}
+
+/// Searches data for markers of active content, a chunk at a time.
+#[derive(Default)]
+struct ActiveContentSearch {
+    /// The end of the previous chunk, in case a marker spans two chunks.
+    carry: Vec<u8>,
+    /// The end of the previous decoded chunk, in case a script URL spans two chunks.
+    decoded_carry: Vec<u8>,
+    /// The text after the `&` of a character reference that continues into the next chunk.
+    reference: Option<Vec<u8>>,
+    handler: HandlerState,
+    /// Whether the previous byte may come before an attribute name, e.g. whitespace, `/` or a
+    /// closing quote.
+    after_separator: bool,
+    found: Option<String>,
+}
+
+/// The state of the search for an event handler attribute, e.g. ` onload =`.
+#[derive(Default)]
+enum HandlerState {
+    #[default]
+    None,
+    /// Found `o` after a separator.
+    O,
+    /// Found `on` followed by the given name.
+    Name(String),
+    /// Found the name followed by whitespace.
+    Space(String),
+}
+
+impl ActiveContentSearch {
+    /// The longest event handler name that is matched.
+    const MAX_HANDLER_LEN: usize = 64;
+
+    /// Search the next chunk of data, returning false once a marker has been found.
+    fn push(&mut self, chunk: &[u8]) -> bool {
+        let chunk = chunk.to_ascii_lowercase();
+        self.carry.extend_from_slice(&chunk);
+        if let Some(marker) = ACTIVE_CONTENT
+            .iter()
+            .find(|m| memchr::memmem::find(&self.carry, m.as_bytes()).is_some())
+        {
+            self.found = Some((*marker).to_owned());
+            return false;
+        }
+        let max_len = ACTIVE_CONTENT.iter().map(|m| m.len()).max().unwrap_or(0);
+        let keep = self.carry.len().min(max_len - 1);
+        self.carry.drain(..self.carry.len() - keep);
+
+        let decoded = self.decode(&chunk);
+        self.decoded_carry.extend(
+            decoded
+                .into_iter()
+                .filter(|b| !matches!(b, b'\t' | b'\n' | b'\r')),
+        );
+        if memchr::memmem::find(&self.decoded_carry, SCRIPT_URL.as_bytes()).is_some() {
+            self.found = Some(SCRIPT_URL.to_owned());
+            return false;
+        }
+        let keep = self.decoded_carry.len().min(SCRIPT_URL.len() - 1);
+        self.decoded_carry.drain(..self.decoded_carry.len() - keep);
+
+        for &b in &chunk {
+            if let Some(handler) = self.push_handler(b) {
+                self.found = Some(handler);
+                return false;
+            }
+            self.after_separator = b.is_ascii_whitespace() || matches!(b, b'/' | b'"' | b'\'');
+        }
+        true
+    }
+
+    /// Decode the character references in a (lowercase) chunk, such as `&#115;`, `&#x73;` or
+    /// `&colon;`. Only references to ASCII characters are decoded, any others are left as they are.
+    fn decode(&mut self, chunk: &[u8]) -> Vec<u8> {
+        let mut decoded = Vec::with_capacity(chunk.len());
+        for &b in chunk {
+            if let Some(mut reference) = self.reference.take() {
+                if b == b';' {
+                    Self::finish_reference(&reference, true, &mut decoded);
+                    continue;
+                }
+                if b.is_ascii_alphanumeric() || (b == b'#' && reference.is_empty()) {
+                    // Leading zeros of a numeric reference don't change its value
+                    let leading_zero = b == b'0' && matches!(reference.as_slice(), b"#" | b"#x");
+                    if !leading_zero {
+                        reference.push(b);
+                    }
+                    if reference.len() > MAX_REFERENCE_LEN {
+                        decoded.push(b'&');
+                        decoded.extend_from_slice(&reference);
+                    } else {
+                        self.reference = Some(reference);
+                    }
+                    continue;
+                }
+                Self::finish_reference(&reference, false, &mut decoded);
+            }
+            if b == b'&' {
+                self.reference = Some(Vec::new());
+            } else {
+                decoded.push(b);
+            }
+        }
+        decoded
+    }
+
+    /// Write the character that a reference refers to, or else the reference itself.
+    fn finish_reference(reference: &[u8], semicolon: bool, decoded: &mut Vec<u8>) {
+        let numeric = |digits: &[u8], radix: u32| {
+            let digits = std::str::from_utf8(digits).ok()?;
+            u8::from_str_radix(digits, radix).ok().filter(u8::is_ascii)
+        };
+        let char = match reference {
+            [b'#', b'x', digits @ ..] => numeric(digits, 16),
+            [b'#', digits @ ..] => numeric(digits, 10),
+            b"colon" if semicolon => Some(b':'),
+            b"tab" if semicolon => Some(b'\t'),
+            b"newline" if semicolon => Some(b'\n'),
+            _ => None,
+        };
+        match char {
+            Some(char) => decoded.push(char),
+            None => {
+                decoded.push(b'&');
+                decoded.extend_from_slice(reference);
+                if semicolon {
+                    decoded.push(b';');
+                }
+            }
+        }
+    }
+
+    /// Advance the event handler search by a byte, returning the attribute name if found.
+    fn push_handler(&mut self, b: u8) -> Option<String> {
+        let state = std::mem::take(&mut self.handler);
+        self.handler = match (state, b) {
+            (HandlerState::O, b'n') => HandlerState::Name(String::new()),
+            (HandlerState::Name(mut name), b) if b.is_ascii_alphabetic() => {
+                if name.len() >= Self::MAX_HANDLER_LEN {
+                    return None;
+                }
+                name.push(b as char);
+                HandlerState::Name(name)
+            }
+            (HandlerState::Name(name) | HandlerState::Space(name), b'=') if !name.is_empty() => {
+                return Some(format!("on{name}"));
+            }
+            (HandlerState::Name(name) | HandlerState::Space(name), b)
+                if b.is_ascii_whitespace() && !name.is_empty() =>
+            {
+                HandlerState::Space(name)
+            }
+            (_, b'o') if self.after_separator => HandlerState::O,
+            _ => HandlerState::None,
+        };
+        None
+    }
+}
+
+impl ContentScanner for ActiveContentScanner {
+    fn scan<'a>(&'a self, upload: Upload<'a>) -> LocalBoxFuture<'a, io::Result<Verdict>> {
+        async move {
+            // Check whether the upload is markup before reading the rest of the data
+            let mut start = Vec::new();
+            upload
+                .content
+                .read_chunks(MARKUP_DETECT_LEN, |chunk| {
+                    start.extend_from_slice(chunk);
+                    false
+                })
+                .await?;
+            if !Self::is_markup(&upload, &start) {
+                return Ok(Verdict::Clean);
+            }
+
+            let mut search = ActiveContentSearch::default();
+            upload
+                .content
+                .read_chunks(SEARCH_CHUNK_SIZE, |chunk| search.push(chunk))
+                .await?;
+            Ok(match search.found {
+                Some(marker) => Verdict::Reject(format!("Active content found: {marker}")),
+                None => Verdict::Clean,
+            })
+        }
+        .boxed_local()
+    }
+}
+
+#[cfg(all(feature = "clamd", unix))]
+pub use clamd::ClamdScanner;
+
+#[cfg(all(feature = "clamd", unix))]
+mod clamd {
+    use crate::scan::{Content, ContentScanner, Upload, Verdict};
+    use futures_core::future::LocalBoxFuture;
+    use futures_util::FutureExt;
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::UnixStream;
+
+    /// The size of the chunks sent to clamd.
+    const CHUNK_SIZE: usize = 65_536; // 64 KiB
+
+    /// Scans uploads using a [ClamAV](https://www.clamav.net/) daemon listening on a Unix socket,
+    /// using the `INSTREAM` command.
+    ///
+    /// Note that clamd limits the size of the data it will scan (`StreamMaxLength`, 25MB by
+    /// default), and larger uploads will fail to be scanned.
+    #[derive(Debug, Clone)]
+    pub struct ClamdScanner {
+        socket: PathBuf,
+    }
+
+    impl ClamdScanner {
+        /// Create a scanner using the clamd socket at the given path, e.g.
+        /// `/var/run/clamav/clamd.ctl`.
+        pub fn new<P: AsRef<Path>>(socket: P) -> Self {
+            Self {
+                socket: socket.as_ref().to_owned(),
+            }
+        }
+
+        async fn instream(&self, content: Content<'_>) -> io::Result<Verdict> {
+            let mut stream = UnixStream::connect(&self.socket).await?;
+            stream.write_all(b"zINSTREAM\0").await?;
+            match content {
+                Content::Bytes(data) => {
+                    for chunk in data.chunks(CHUNK_SIZE) {
+                        write_chunk(&mut stream, chunk).await?;
+                    }
+                }
+                #[cfg(feature = "tempfile")]
+                Content::File(path) => {
+                    let mut file = tokio::fs::File::open(path).await?;
+                    let mut buf = vec![0; CHUNK_SIZE];
+                    loop {
+                        let len = file.read(&mut buf).await?;
+                        if len == 0 {
+                            break;
+                        }
+                        write_chunk(&mut stream, &buf[..len]).await?;
+                    }
+                }
+            }
+            // A zero length chunk ends the stream
+            stream.write_all(&[0; 4]).await?;
+
+            let mut reply = Vec::new();
+            stream.read_to_end(&mut reply).await?;
+            parse_reply(&reply)
+        }
+    }
+
+    async fn write_chunk(stream: &mut UnixStream, chunk: &[u8]) -> io::Result<()> {
+        let len = u32::try_from(chunk.len()).expect("chunk is larger than 4GiB");
+        stream.write_all(&len.to_be_bytes()).await?;
+        stream.write_all(chunk).await
+    }
+
+    /// Parse a reply such as `stream: OK` or `stream: Eicar-Signature FOUND`.
+    fn parse_reply(reply: &[u8]) -> io::Result<Verdict> {
+        let reply = String::from_utf8_lossy(reply);
+        let reply = reply.trim_end_matches(['\0', '\n']);
+        let result = reply.strip_prefix("stream: ").unwrap_or(reply);
+        if result == "OK" {
+            Ok(Verdict::Clean)
+        } else if let Some(signature) = result.strip_suffix(" FOUND") {
+            Ok(Verdict::Reject(format!("Malware found: {signature}")))
+        } else {
+            Err(io::Error::other(format!("Unexpected clamd reply: {reply}")))
+        }
+    }
+
+    impl ContentScanner for ClamdScanner {
+        fn scan<'a>(&'a self, upload: Upload<'a>) -> LocalBoxFuture<'a, io::Result<Verdict>> {
+            self.instream(upload.content).boxed_local()
+        }
+    }
+
+    #[cfg(all(test, feature = "tempfile"))]
+    mod tests {
+        use crate::scan::clamd::ClamdScanner;
+        use crate::scan::{Content, ContentScanner, Upload, Verdict};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::UnixListener;
+
+        /// A fake clamd which handles a single `INSTREAM` command, reporting data containing
+        /// `EICAR` as infected.
+        async fn fake_clamd(listener: UnixListener) {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut command = [0; 10];
+            stream.read_exact(&mut command).await.unwrap();
+            assert_eq!(&command, b"zINSTREAM\0");
+            let mut data = Vec::new();
+            loop {
+                let len = stream.read_u32().await.unwrap() as usize;
+                if len == 0 {
+                    break;
+                }
+                let start = data.len();
+                data.resize(start + len, 0);
+                stream.read_exact(&mut data[start..]).await.unwrap();
+            }
+            let infected = data.windows(5).any(|w| w == b"EICAR");
+            let reply: &[u8] = if infected {
+                b"stream: Eicar-Test-Signature FOUND\0"
+            } else {
+                b"stream: OK\0"
+            };
+            stream.write_all(reply).await.unwrap();
+        }
+
+        async fn scan(data: &[u8]) -> Verdict {
+            let dir = tempfile::tempdir().unwrap();
+            let socket = dir.path().join("clamd.sock");
+            let listener = UnixListener::bind(&socket).unwrap();
+            let server = actix_rt::spawn(fake_clamd(listener));
+            let upload = Upload {
+                field_name: "file",
+                file_name: None,
+                content_type: None,
+                detected_content_type: None,
+                content: Content::Bytes(data),
+            };
+            let verdict = ClamdScanner::new(&socket).scan(upload).await.unwrap();
+            server.await.unwrap();
+            verdict
+        }
+
+        #[actix_rt::test]
+        async fn test_clamd() {
+            assert_eq!(scan(b"Hello, world!").await, Verdict::Clean);
+            let infected = scan(&[&[0; 100_000][..], b"EICAR"].concat()).await;
+            assert!(matches!(infected, Verdict::Reject(_)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bytes::Bytes;
+    use crate::scan::{ActiveContentScanner, ActiveContentSearch, ScanConfig};
+    use crate::MultipartForm;
+    use actix_web::http::StatusCode;
+    use actix_web::{web, App, HttpResponse, Responder};
+    use awc::Client;
+
+    #[test]
+    fn test_find_active_content() {
+        let find = ActiveContentScanner::find_active_content;
+        assert_eq!(
+            find(b"<svg><SCRIPT>alert(1)</script></svg>").unwrap(),
+            "<script"
+        );
+        assert_eq!(find(b"<svg onload = \"alert(1)\"/>").unwrap(), "onload");
+        assert_eq!(
+            find(b"<a href=\"JavaScript:alert(1)\">").unwrap(),
+            "javascript:"
+        );
+        assert!(find(b"<svg><text>one=1, bonus=2</text></svg>").is_none());
+    }
+
+    #[test]
+    fn test_find_event_handler_after_separator() {
+        let find = ActiveContentScanner::find_active_content;
+        assert_eq!(find(b"<svg/onload=alert(1)>").unwrap(), "onload");
+        assert_eq!(find(b"<img src=x\"onerror=alert(1)>").unwrap(), "onerror");
+        assert_eq!(find(b"<img src='x'onerror=alert(1)>").unwrap(), "onerror");
+    }
+
+    #[test]
+    fn test_find_encoded_script_url() {
+        let find = ActiveContentScanner::find_active_content;
+        assert_eq!(
+            find(b"<a href=\"java&#115;cript:alert(1)\">").unwrap(),
+            "javascript:"
+        );
+        assert_eq!(
+            find(b"<a href=\"&#X6A;ava&#0000009;script&colon;alert(1)\">").unwrap(),
+            "javascript:"
+        );
+        // Escaped markup is text rather than a tag
+        assert!(find(b"<svg><text>&#60;script&#62; &amp; java</text></svg>").is_none());
+
+        let mut search = ActiveContentSearch::default();
+        assert!(search.push(b"<a href=\"java&#11"));
+        assert!(!search.push(b"5;cript:alert(1)\">"));
+        assert_eq!(search.found.unwrap(), "javascript:");
+    }
+
+    #[test]
+    fn test_find_active_content_across_chunks() {
+        let mut search = ActiveContentSearch::default();
+        assert!(search.push(b"<svg><scr"));
+        assert!(!search.push(b"ipt>alert(1)</script></svg>"));
+        assert_eq!(search.found.unwrap(), "<script");
+
+        let mut search = ActiveContentSearch::default();
+        assert!(search.push(b"<svg onlo"));
+        assert!(!search.push(b"ad=\"alert(1)\"/>"));
+        assert_eq!(search.found.unwrap(), "onload");
+    }
+
+    #[derive(MultipartForm)]
+    struct TestScan {
+        _file: Bytes,
+    }
+
+    async fn test_scan_route(_: MultipartForm<TestScan>) -> impl Responder {
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_active_content() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_scan_route))
+                .app_data(ScanConfig::default().scanner(ActiveContentScanner))
+        });
+        let send = |file_name: &str, data: &str| {
+            let body = format!(
+                "--AaB03x\r\n\
+                Content-Disposition: form-data; name=\"_file\"; filename=\"{file_name}\"\r\n\
+                \r\n\
+                {data}\r\n\
+                --AaB03x--\r\n"
+            );
+            Client::default()
+                .post(srv.url("/"))
+                .content_type("multipart/form-data; boundary=AaB03x")
+                .send_body(body)
+        };
+
+        let response = send("a.svg", "<svg><circle r=\"1\"/></svg>").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send("a.svg", "<svg><script>alert(1)</script></svg>")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // Detected from the data, regardless of the filename
+        let response = send("a.png", "<!DOCTYPE html><body onload=\"alert(1)\">")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // Not markup, so not checked
+        let response = send("a.txt", "<script> is a tag").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
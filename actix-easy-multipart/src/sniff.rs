@@ -0,0 +1,223 @@
+//! Detection of the content type of a part from its first bytes (the "magic bytes"), see
+//! [`MultipartFormConfig::sniff_content_type`](crate::MultipartFormConfig::sniff_content_type).
+//!
+//! The declared `Content-Type` of a part is chosen by the client, so can't be trusted. The
+//! detected content type is available using [`Field::detected_content_type`], and can be checked
+//! against the declared content type or an allowlist, either for all file parts using the
+//! [`MultipartFormConfig`](crate::MultipartFormConfig) or for a single field using the
+//! `#[multipart(content_types = "")]` attribute:
+//!
+//! ```
+//! # use actix_easy_multipart::bytes::Bytes;
+//! # use actix_easy_multipart::MultipartForm;
+//! #[derive(MultipartForm)]
+//! struct Form {
+//!     #[multipart(content_types = "image/png, image/jpeg")]
+//!     avatar: Bytes,
+//! }
+//! ```
+//!
+//! Formats without magic bytes, such as plain text, are not detected, in which case the declared
+//! content type is checked against the allowlist instead, unless it is a format that would have
+//! been detected.
+use crate::Field;
+use actix_web::http::StatusCode;
+use actix_web::ResponseError;
+use derive_more::{Display, Error};
+use mime::Mime;
+
+/// The number of bytes read to detect the content type, which is enough for all the formats
+/// supported by [`infer`].
+const SNIFF_LEN: usize = 8192;
+
+/// The content type sniffing options of a form.
+#[derive(Clone, Default)]
+pub(crate) struct Sniffing {
+    pub(crate) enabled: bool,
+    pub(crate) reject_mismatch: bool,
+    pub(crate) allowed: Option<Vec<Mime>>,
+}
+
+/// A content type sniffing error.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum SniffError {
+    /// The detected content type does not match the declared content type
+    #[display(
+        fmt = "Detected content type {} does not match the declared content type {}",
+        detected,
+        declared
+    )]
+    Mismatch { declared: Mime, detected: Mime },
+    /// The content type is not in the allowlist
+    #[display(fmt = "Content type {} is not allowed", _0)]
+    NotAllowed(#[error(not(source))] String),
+}
+
+impl ResponseError for SniffError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    }
+}
+
+impl Sniffing {
+    /// Detect the content type of the field, if enabled or if the field has an allowlist, and
+    /// check it against the declared content type and the allowlist.
+    ///
+    /// The allowlist from the field's attribute takes precedence over the configured allowlist,
+    /// which only applies to files (parts with a filename).
+    pub(crate) async fn sniff(
+        &self,
+        field: Field,
+        field_allowed: Option<&[&str]>,
+    ) -> Result<Field, crate::Error> {
+        if !self.enabled && field_allowed.is_none() {
+            return Ok(field);
+        }
+        let (field, head) = field.peek(SNIFF_LEN).await?;
+        let detected = infer::get(&head).and_then(|kind| kind.mime_type().parse::<Mime>().ok());
+        let field = field.with_detected_content_type(detected.clone());
+        let field_error = |source: SniffError| crate::Error::Field {
+            field_name: field.name().to_owned(),
+            source: source.into(),
+        };
+
+        if self.reject_mismatch {
+            if let (Some(declared), Some(detected)) = (field.content_type(), &detected) {
+                // A generic binary type is not a claim about the content
+                if *declared != mime::APPLICATION_OCTET_STREAM
+                    && declared.essence_str() != detected.essence_str()
+                {
+                    return Err(field_error(SniffError::Mismatch {
+                        declared: declared.clone(),
+                        detected: detected.clone(),
+                    }));
+                }
+            }
+        }
+
+        let allowed = match (field_allowed, &self.allowed) {
+            (Some(allowed), _) => Some(
+                allowed
+                    .iter()
+                    .map(|allowed| {
+                        allowed.parse::<Mime>().unwrap_or_else(|_| {
+                            panic!("Unable to parse allowed content type `{allowed}`")
+                        })
+                    })
+                    .collect(),
+            ),
+            (None, Some(allowed)) if field.file_name().is_some() => Some(allowed.clone()),
+            _ => None,
+        };
+        if let Some(allowed) = allowed {
+            // If the declared type could have been detected then the data is not of that type
+            let content_type = detected.as_ref().or_else(|| {
+                field
+                    .content_type()
+                    .filter(|declared| !infer::is_mime_supported(declared.essence_str()))
+            });
+            if !content_type.is_some_and(|ct| allowed.iter().any(|a| matches(a, ct))) {
+                let content_type =
+                    content_type.map_or_else(|| "unknown".to_owned(), Mime::to_string);
+                return Err(field_error(SniffError::NotAllowed(content_type)));
+            }
+        }
+        Ok(field)
+    }
+}
+
+/// Check whether a content type matches an allowlist entry, which may be a wildcard such as
+/// `image/*`. Parameters are ignored.
+fn matches(allowed: &Mime, content_type: &Mime) -> bool {
+    if allowed.type_() == mime::STAR {
+        return true;
+    }
+    if allowed.subtype() == mime::STAR {
+        return allowed.type_() == content_type.type_();
+    }
+    allowed.essence_str() == content_type.essence_str()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bytes::Bytes;
+    use crate::MultipartForm;
+    use crate::MultipartFormConfig;
+    use actix_web::http::StatusCode;
+    use actix_web::{web, App, HttpResponse, Responder};
+    use awc::Client;
+
+    /// The start of a PNG file.
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR";
+
+    fn body(field: &str, content_type: &str, data: &[u8]) -> Vec<u8> {
+        let mut body = format!(
+            "--AaB03x\r\n\
+            Content-Disposition: form-data; name=\"{field}\"; filename=\"a\"\r\n\
+            Content-Type: {content_type}\r\n\
+            \r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n--AaB03x--\r\n");
+        body
+    }
+
+    #[derive(MultipartForm)]
+    struct TestSniff {
+        #[multipart(content_types = "image/*")]
+        image: Option<Bytes>,
+        file: Option<Bytes>,
+    }
+
+    async fn test_sniff_route(form: MultipartForm<TestSniff>) -> impl Responder {
+        let field = form.image.as_ref().or(form.file.as_ref()).unwrap();
+        assert_eq!(field.detected_content_type, Some(mime::IMAGE_PNG));
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_sniff() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_sniff_route))
+                .app_data(
+                    MultipartFormConfig::default()
+                        .sniff_content_type(true)
+                        .reject_content_type_mismatch(true)
+                        .allowed_content_types(vec![mime::IMAGE_PNG, mime::TEXT_PLAIN]),
+                )
+        });
+        let send = |body: Vec<u8>| {
+            Client::default()
+                .post(srv.url("/"))
+                .content_type("multipart/form-data; boundary=AaB03x")
+                .send_body(body)
+        };
+
+        let response = send(body("image", "image/png", PNG)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send(body("file", "application/octet-stream", PNG))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // A PNG file declared as a JPEG
+        let response = send(body("image", "image/jpeg", PNG)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        // Plain text is not detected, so the declared type is checked against the field's
+        // allowlist
+        let response = send(body("image", "text/plain", b"Hello, world!"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        // A PDF file is not in the configured allowlist
+        let response = send(body("file", "application/pdf", b"%PDF-1.7\n"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
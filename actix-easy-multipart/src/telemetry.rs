@@ -0,0 +1,273 @@
+//! Tracing spans and metrics for form extraction, enabled using the `tracing` and `metrics`
+//! features respectively.
+//!
+//! The following metrics are emitted:
+//!
+//! - `multipart_bytes_received_total`: Counter of bytes read from form parts.
+//! - `multipart_parts_total`: Counter of parts processed.
+//! - `multipart_rejections_total`: Counter of rejected forms, labelled by the error `code` (see
+//!   [`ProblemDetails`](crate::problem::ProblemDetails)).
+//! - `multipart_tempfile_bytes_written_total`: Counter of bytes written to temporary files.
+//! - `multipart_form_duration_seconds`: Histogram of the time taken to extract a form.
+//! - `multipart_field_duration_seconds`: Histogram of the time taken to read a part.
+//! - `multipart_field_bytes`: Histogram of the size of each part.
+use crate::{Error, Field, Limits};
+use std::future::Future;
+#[cfg(any(feature = "tracing", feature = "metrics"))]
+use std::time::Instant;
+
+/// Records a span and metrics for the extraction of a whole form.
+pub(crate) struct FormRecorder {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    start: Instant,
+}
+
+impl FormRecorder {
+    // The form type is only used to name the span
+    #[cfg_attr(not(feature = "tracing"), allow(clippy::extra_unused_type_parameters))]
+    pub(crate) fn start<T>() -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "multipart_form",
+                form = std::any::type_name::<T>(),
+                bytes = tracing::field::Empty,
+                duration_ms = tracing::field::Empty,
+                outcome = tracing::field::Empty,
+            ),
+            #[cfg(any(feature = "tracing", feature = "metrics"))]
+            start: Instant::now(),
+        }
+    }
+
+    pub(crate) fn instrument<F: Future>(&self, fut: F) -> impl Future<Output = F::Output> {
+        #[cfg(feature = "tracing")]
+        return tracing::Instrument::instrument(fut, self.span.clone());
+        #[cfg(not(feature = "tracing"))]
+        return fut;
+    }
+
+    // The limits are only recorded in the span
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn finish<T>(self, limits: &Limits, result: &Result<T, Error>) {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("bytes", limits.bytes_consumed());
+            self.span
+                .record("duration_ms", self.start.elapsed().as_millis() as u64);
+            self.span.record("outcome", outcome(result));
+        }
+        #[cfg(feature = "metrics")]
+        {
+            metrics::histogram!("multipart_form_duration_seconds")
+                .record(self.start.elapsed().as_secs_f64());
+            if result.is_err() {
+                metrics::counter!("multipart_rejections_total", "code" => outcome(result))
+                    .increment(1);
+            }
+        }
+    }
+}
+
+/// Records a span and metrics for reading a single part.
+pub(crate) struct FieldRecorder {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    start: Instant,
+    #[cfg(any(feature = "tracing", feature = "metrics"))]
+    bytes_consumed: usize,
+}
+
+impl FieldRecorder {
+    // The field is only described by the span
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn start(field: &Field, limits: &Limits) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                "multipart_field",
+                name = field.name(),
+                filename = field.file_name(),
+                content_type = field.content_type().map(|m| m.as_ref()),
+                bytes = tracing::field::Empty,
+                duration_ms = tracing::field::Empty,
+                outcome = tracing::field::Empty,
+            ),
+            #[cfg(any(feature = "tracing", feature = "metrics"))]
+            start: Instant::now(),
+            #[cfg(any(feature = "tracing", feature = "metrics"))]
+            bytes_consumed: limits.bytes_consumed(),
+        }
+    }
+
+    pub(crate) fn instrument<F: Future>(&self, fut: F) -> impl Future<Output = F::Output> {
+        #[cfg(feature = "tracing")]
+        return tracing::Instrument::instrument(fut, self.span.clone());
+        #[cfg(not(feature = "tracing"))]
+        return fut;
+    }
+
+    // The result is only recorded in the span
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn finish(self, limits: &Limits, result: &Result<(), Error>) {
+        #[cfg(any(feature = "tracing", feature = "metrics"))]
+        let (bytes, duration) = (
+            limits.bytes_consumed() - self.bytes_consumed,
+            self.start.elapsed(),
+        );
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("bytes", bytes);
+            self.span.record("duration_ms", duration.as_millis() as u64);
+            self.span.record("outcome", outcome(result));
+        }
+        #[cfg(feature = "metrics")]
+        {
+            metrics::counter!("multipart_parts_total").increment(1);
+            metrics::counter!("multipart_bytes_received_total").increment(bytes as u64);
+            metrics::histogram!("multipart_field_bytes").record(bytes as f64);
+            metrics::histogram!("multipart_field_duration_seconds").record(duration.as_secs_f64());
+        }
+    }
+}
+
+/// Records bytes written to a temporary file.
+#[cfg(all(feature = "tempfile", feature = "metrics"))]
+pub(crate) fn record_tempfile_bytes(bytes: usize) {
+    metrics::counter!("multipart_tempfile_bytes_written_total").increment(bytes as u64);
+}
+
+#[cfg(any(feature = "tracing", feature = "metrics"))]
+fn outcome<T>(result: &Result<T, Error>) -> &'static str {
+    match result {
+        Ok(_) => "ok",
+        Err(err) => crate::problem::error_code(err),
+    }
+}
+
+#[cfg(all(test, feature = "tracing", feature = "metrics"))]
+mod tests {
+    use crate::text::Text;
+    use crate::MultipartForm;
+    use actix_web::test::TestRequest;
+    use actix_web::FromRequest;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::Subscriber;
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::Layer;
+
+    /// Collects the names of the spans created, and the outcomes recorded in them.
+    #[derive(Clone, Default)]
+    struct SpanLayer(Arc<Mutex<Vec<String>>>);
+
+    impl Visit for SpanLayer {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "outcome" {
+                self.0.lock().unwrap().push(format!("outcome={value}"));
+            }
+        }
+
+        fn record_debug(&mut self, _: &Field, _: &dyn std::fmt::Debug) {}
+    }
+
+    impl<S: Subscriber> Layer<S> for SpanLayer {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _: &Id, _: Context<'_, S>) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(attrs.metadata().name().to_owned());
+        }
+
+        fn on_record(&self, _: &Id, values: &Record<'_>, _: Context<'_, S>) {
+            values.record(&mut self.clone());
+        }
+    }
+
+    #[derive(MultipartForm)]
+    struct TelemetryForm {
+        name: Text<String>,
+    }
+
+    async fn extract(body: &'static str) -> Result<MultipartForm<TelemetryForm>, actix_web::Error> {
+        let (req, mut payload) = TestRequest::post()
+            .insert_header(("content-type", "multipart/form-data; boundary=AaB03x"))
+            .set_payload(body)
+            .to_http_parts();
+        MultipartForm::<TelemetryForm>::from_request(&req, &mut payload).await
+    }
+
+    #[actix_rt::test]
+    async fn test_telemetry() {
+        // The subscriber and recorder are set for the current thread, so the form is extracted
+        // without a test server
+        let spans = SpanLayer::default();
+        let _subscriber =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(spans.clone()));
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let _recorder = metrics::set_default_local_recorder(&recorder);
+
+        let form = extract(
+            "--AaB03x\r\n\
+            Content-Disposition: form-data; name=\"name\"\r\n\
+            \r\n\
+            value\r\n\
+            --AaB03x--\r\n",
+        )
+        .await
+        .unwrap();
+        assert_eq!(&*form.name, "value");
+        assert!(extract("--AaB03x--\r\n").await.is_err());
+
+        assert_eq!(
+            *spans.0.lock().unwrap(),
+            vec![
+                "multipart_form",
+                "multipart_field",
+                "outcome=ok",
+                "outcome=ok",
+                "multipart_form",
+                "outcome=missing_field",
+            ]
+        );
+
+        let metrics = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let labels = key
+                    .key()
+                    .labels()
+                    .map(|l| format!("{}={}", l.key(), l.value()))
+                    .collect::<Vec<_>>();
+                (key.key().name().to_owned(), labels, value)
+            })
+            .collect::<Vec<_>>();
+        let counter = |name: &str, labels: &[&str]| {
+            metrics.iter().find_map(|(n, l, value)| match value {
+                DebugValue::Counter(value) if n == name && l == labels => Some(*value),
+                _ => None,
+            })
+        };
+        assert_eq!(counter("multipart_parts_total", &[]), Some(1));
+        assert_eq!(
+            counter("multipart_rejections_total", &["code=missing_field"]),
+            Some(1)
+        );
+        let histogram = |name: &str| {
+            metrics.iter().find_map(|(n, _, value)| match value {
+                DebugValue::Histogram(values) if n == name => Some(values.len()),
+                _ => None,
+            })
+        };
+        assert_eq!(histogram("multipart_form_duration_seconds"), Some(2));
+        assert_eq!(histogram("multipart_field_bytes"), Some(1));
+    }
+}
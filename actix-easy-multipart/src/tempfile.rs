@@ -1,7 +1,7 @@
 //! Writes a field to a temporary file on disk.
+use crate::scan::Content;
 use crate::tempfile::TempfileError::FileIo;
-use crate::Field;
-use crate::{field_mime, FieldReader, Limits};
+use crate::{file_name, Field, FieldReader, Limits};
 use actix_web::http::StatusCode;
 use actix_web::{web, HttpRequest, ResponseError};
 use derive_more::{Display, Error};
@@ -20,10 +20,24 @@ pub struct Tempfile {
     pub file: NamedTempFile,
     /// The value of the `content-type` header.
     pub content_type: Option<Mime>,
-    /// The `filename` value in the `content-disposition` header.
+    /// The content type detected from the file, see [`Field::detected_content_type`].
+    pub detected_content_type: Option<Mime>,
+    /// The `filename*` or `filename` value in the `content-disposition` header, which is sanitized
+    /// if [`TempfileConfig::sanitize_file_name`] is enabled.
     pub file_name: Option<String>,
     /// The size in bytes of the file.
     pub size: usize,
+    /// The digests of the file, see [`TempfileConfig::digest`] and
+    /// [`DigestConfig`](crate::digest::DigestConfig).
+    #[cfg(feature = "digest")]
+    pub digests: crate::digest::Digests,
+}
+
+impl Tempfile {
+    /// The filename, sanitized so that it is safe to use on disk, see [`file_name::sanitize`].
+    pub fn sanitized_file_name(&self) -> Option<String> {
+        self.file_name.as_deref().and_then(file_name::sanitize)
+    }
 }
 
 impl<'t> FieldReader<'t> for Tempfile {
@@ -33,7 +47,14 @@ impl<'t> FieldReader<'t> for Tempfile {
         async move {
             let config = TempfileConfig::from_req(req);
             let field_name = field.name().to_owned();
+            let file_name = if config.sanitize_file_name {
+                field.file_name().as_deref().and_then(file_name::sanitize)
+            } else {
+                field.file_name()
+            };
             let mut size = 0;
+            #[cfg(feature = "digest")]
+            let mut hasher = crate::digest::Hasher::new(req, &field, config.digests)?;
 
             let file = if let Some(dir) = &config.directory {
                 NamedTempFile::new_in(dir)
@@ -49,29 +70,39 @@ impl<'t> FieldReader<'t> for Tempfile {
 
             while let Some(chunk) = field.try_next().await? {
                 limits.try_consume_limits(chunk.len(), false)?;
+                limits.try_consume_disk_limits(chunk.len())?;
                 size += chunk.len();
+                #[cfg(feature = "digest")]
+                hasher.update(&chunk);
                 file_async
                     .write_all(chunk.as_ref())
                     .await
                     .map_err(|e| config.map_error(req, &field_name, FileIo(e)))?;
+                #[cfg(feature = "metrics")]
+                crate::telemetry::record_tempfile_bytes(chunk.len());
             }
             file_async
                 .flush()
                 .await
                 .map_err(|e| config.map_error(req, &field_name, FileIo(e)))?;
+            crate::scan::scan_field(req, &field, Content::File(file.path())).await?;
 
             Ok(Tempfile {
                 file,
-                content_type: field_mime(&field),
-                file_name: field
-                    .content_disposition()
-                    .get_filename()
-                    .map(str::to_owned),
+                content_type: field.content_type().cloned(),
+                detected_content_type: field.detected_content_type().cloned(),
+                file_name,
                 size,
+                #[cfg(feature = "digest")]
+                digests: hasher.finish()?,
             })
         }
         .boxed_local()
     }
+
+    fn file_only() -> bool {
+        true
+    }
 }
 
 #[derive(Debug, Display, Error)]
@@ -93,11 +124,17 @@ impl ResponseError for TempfileError {
 pub struct TempfileConfig {
     err_handler: Option<Arc<dyn Fn(TempfileError, &HttpRequest) -> actix_web::Error + Send + Sync>>,
     directory: Option<PathBuf>,
+    sanitize_file_name: bool,
+    #[cfg(feature = "digest")]
+    digests: crate::digest::Algorithms,
 }
 
 const DEFAULT_CONFIG: TempfileConfig = TempfileConfig {
     err_handler: None,
     directory: None,
+    sanitize_file_name: false,
+    #[cfg(feature = "digest")]
+    digests: crate::digest::Algorithms::NONE,
 };
 
 impl TempfileConfig {
@@ -134,6 +171,22 @@ impl TempfileConfig {
         self.directory = Some(dir.as_ref().to_owned());
         self
     }
+
+    /// Set whether [`Tempfile::file_name`] is sanitized, see [`file_name::sanitize`]. By default
+    /// the filename is given as sent by the client.
+    pub fn sanitize_file_name(mut self, sanitize_file_name: bool) -> Self {
+        self.sanitize_file_name = sanitize_file_name;
+        self
+    }
+
+    /// Add an algorithm to compute the digest of each file with while it is written, in addition
+    /// to those of the [`DigestConfig`](crate::digest::DigestConfig). By default no digests are
+    /// computed.
+    #[cfg(feature = "digest")]
+    pub fn digest(mut self, algorithm: crate::digest::Algorithm) -> Self {
+        self.digests.insert(algorithm);
+        self
+    }
 }
 
 impl Default for TempfileConfig {
@@ -144,12 +197,13 @@ impl Default for TempfileConfig {
 
 #[cfg(test)]
 mod tests {
-    use crate::tempfile::Tempfile;
+    use crate::tempfile::{Tempfile, TempfileConfig};
     use crate::tests::send_form;
-    use crate::MultipartForm;
+    use crate::{MultipartForm, MultipartFormConfig};
     use actix_multipart_rfc7578::client::multipart;
     use actix_web::http::StatusCode;
     use actix_web::{web, App, HttpResponse, Responder};
+    use awc::Client;
     use std::io::{Cursor, Read};
 
     #[derive(MultipartForm)]
@@ -177,4 +231,61 @@ mod tests {
         let response = send_form(&srv, form, "/").await;
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    #[actix_rt::test]
+    async fn test_disk_limit() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_file_route))
+                .app_data(MultipartFormConfig::default().disk_limit(10))
+        });
+
+        // Exceeds the 10 byte disk limit
+        let mut form = multipart::Form::default();
+        let bytes = Cursor::new("Hello, world!");
+        form.add_reader_file_with_mime("file", bytes, "testfile.txt", mime::TEXT_PLAIN);
+        let response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    async fn test_sanitize_route(form: MultipartForm<FileForm>) -> impl Responder {
+        assert_eq!(form.file.file_name.as_deref(), Some("passwd"));
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_sanitize_file_name() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_sanitize_route))
+                .app_data(TempfileConfig::default().sanitize_file_name(true))
+        });
+
+        let body = "--AaB03x\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"; \
+            filename*=UTF-8''..%2F..%2Fetc%2Fpasswd\r\n\
+            \r\n\
+            Hello, world!\r\n\
+            --AaB03x--\r\n";
+        let response = Client::default()
+            .post(srv.url("/"))
+            .content_type("multipart/form-data; boundary=AaB03x")
+            .send_body(body)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_urlencoded_file() {
+        let srv = actix_test::start(|| App::new().route("/", web::post().to(test_file_route)));
+
+        // A file can't be sent in a urlencoded form, so the field is missing
+        let response = Client::default()
+            .post(srv.url("/"))
+            .send_form(&[("file", "Hello, world!")])
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
 }
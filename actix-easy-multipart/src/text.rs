@@ -1,19 +1,28 @@
 //! Deserializes a field from plain text.
 use crate::bytes::Bytes;
-use crate::{field_mime, FieldReader, Limits};
-use actix_multipart::Field;
+use crate::{Field, FieldReader, Limits, NotSent};
 use actix_web::http::StatusCode;
 use actix_web::{web, Error, HttpRequest, ResponseError};
 use derive_more::{Deref, DerefMut, Display, Error};
+use encoding_rs::{Encoding, UTF_8};
 use futures_core::future::LocalBoxFuture;
 use futures_util::FutureExt;
 use serde::de::DeserializeOwned;
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
 use std::sync::Arc;
+use unicode_normalization::UnicodeNormalization;
 
 /// Deserialize from plain text.
 ///
 /// Internally this uses [`serde_plain`] for deserialization, which supports primitive types
 /// including strings, numbers, and simple enums.
+///
+/// The text is decoded using the `charset` parameter of the field's `Content-Type`, or else the
+/// value of the form's `_charset_` field, or else the [`TextConfig::default_charset`]. Any
+/// charset supported by [`encoding_rs`] may be used.
 #[derive(Debug, Deref, DerefMut)]
 pub struct Text<T: DeserializeOwned>(pub T);
 
@@ -30,35 +39,53 @@ impl<'t, T: DeserializeOwned + 'static> FieldReader<'t> for Text<T> {
         async move {
             let config = TextConfig::from_req(req);
             let field_name = field.name().to_owned();
+            let Some(text) = read_text(req, field, limits, config).await? else {
+                return Err(NotSent::error(field_name));
+            };
 
-            if config.validate_content_type {
-                let valid = if let Some(mime) = field_mime(&field) {
-                    mime.subtype() == mime::PLAIN || mime.suffix() == Some(mime::PLAIN)
-                } else {
-                    // https://www.rfc-editor.org/rfc/rfc7578#section-4.4
-                    // content type defaults to text/plain, so None should be considered valid
-                    true
-                };
-                if !valid && config.validate_content_type {
-                    return Err(crate::Error::Field {
-                        field_name,
-                        source: config.map_error(req, TextError::ContentType),
-                    });
+            Ok(Text(serde_plain::from_str(&text).map_err(|e| {
+                crate::Error::Field {
+                    field_name,
+                    source: config.map_error(req, TextError::Deserialize(e)),
                 }
-            }
+            })?))
+        }
+        .boxed_local()
+    }
+}
+
+/// Parse from plain text using the [`FromStr`] implementation of `T`.
+///
+/// This is useful for types which don't support deserializing from a string with [`serde_plain`],
+/// such as [`IpAddr`](std::net::IpAddr). The text is decoded in the same way as [`Text`].
+#[derive(Debug, Deref, DerefMut)]
+pub struct TextFromStr<T: FromStr>(pub T);
+
+impl<T: FromStr> TextFromStr<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
 
-            let bytes = Bytes::read_field(req, field, limits).await?;
+impl<'t, T> FieldReader<'t> for TextFromStr<T>
+where
+    T: FromStr + 'static,
+    T::Err: fmt::Display,
+{
+    type Future = LocalBoxFuture<'t, Result<Self, crate::Error>>;
 
-            let text =
-                std::str::from_utf8(bytes.data.as_ref()).map_err(|e| crate::Error::Field {
-                    field_name: field_name.clone(),
-                    source: config.map_error(req, TextError::Utf8Error(e)),
-                })?;
+    fn read_field(req: &'t HttpRequest, field: Field, limits: &'t mut Limits) -> Self::Future {
+        async move {
+            let config = TextConfig::from_req(req);
+            let field_name = field.name().to_owned();
+            let Some(text) = read_text(req, field, limits, config).await? else {
+                return Err(NotSent::error(field_name));
+            };
 
-            Ok(Text(serde_plain::from_str(text).map_err(|e| {
+            Ok(TextFromStr(text.parse().map_err(|e: T::Err| {
                 crate::Error::Field {
                     field_name,
-                    source: config.map_error(req, TextError::Deserialize(e)),
+                    source: config.map_error(req, TextError::Parse(e.to_string())),
                 }
             })?))
         }
@@ -66,6 +93,254 @@ impl<'t, T: DeserializeOwned + 'static> FieldReader<'t> for Text<T> {
     }
 }
 
+/// Parse a list of items separated by `S`, such as `a,b,c`, using the [`FromStr`] implementation
+/// of `T`.
+///
+/// Whitespace around each item is trimmed, and an empty field is an empty list. The number of
+/// items is limited by [`TextConfig::max_items`]. The text is decoded in the same way as [`Text`].
+///
+/// ```
+/// # use actix_easy_multipart::text::{Delimited, Semicolon};
+/// # use actix_easy_multipart::MultipartForm;
+/// #[derive(MultipartForm)]
+/// struct Form {
+///     ids: Delimited<u32>,
+///     tags: Delimited<String, Semicolon>,
+/// }
+/// ```
+#[derive(Debug, Deref, DerefMut)]
+pub struct Delimited<T: FromStr, S: Separator = Comma>(
+    #[deref]
+    #[deref_mut]
+    pub Vec<T>,
+    PhantomData<S>,
+);
+
+impl<T: FromStr, S: Separator> Delimited<T, S> {
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<'t, T, S> FieldReader<'t> for Delimited<T, S>
+where
+    T: FromStr + 'static,
+    T::Err: fmt::Display,
+    S: Separator + 'static,
+{
+    type Future = LocalBoxFuture<'t, Result<Self, crate::Error>>;
+
+    fn read_field(req: &'t HttpRequest, field: Field, limits: &'t mut Limits) -> Self::Future {
+        async move {
+            let config = TextConfig::from_req(req);
+            let field_name = field.name().to_owned();
+            let Some(text) = read_text(req, field, limits, config).await? else {
+                return Err(NotSent::error(field_name));
+            };
+
+            let field_error = |err: TextError| crate::Error::Field {
+                field_name: field_name.clone(),
+                source: config.map_error(req, err),
+            };
+
+            let mut items = Vec::new();
+            if !text.trim().is_empty() {
+                for item in text.split(S::SEPARATOR) {
+                    if items.len() >= config.max_items {
+                        return Err(field_error(TextError::TooManyItems(config.max_items)));
+                    }
+                    let item = item.trim().parse().map_err(|e: T::Err| {
+                        field_error(TextError::Parse(format!("item {}: {}", items.len() + 1, e)))
+                    })?;
+                    items.push(item);
+                }
+            }
+            Ok(Delimited(items, PhantomData))
+        }
+        .boxed_local()
+    }
+}
+
+/// The separator between the items of a [`Delimited`] field.
+pub trait Separator {
+    const SEPARATOR: char;
+}
+
+/// Items separated by `,`.
+#[derive(Debug)]
+pub struct Comma;
+
+impl Separator for Comma {
+    const SEPARATOR: char = ',';
+}
+
+/// Items separated by `;`.
+#[derive(Debug)]
+pub struct Semicolon;
+
+impl Separator for Semicolon {
+    const SEPARATOR: char = ';';
+}
+
+/// Items separated by `|`.
+#[derive(Debug)]
+pub struct Pipe;
+
+impl Separator for Pipe {
+    const SEPARATOR: char = '|';
+}
+
+/// Items on separate lines.
+#[derive(Debug)]
+pub struct Newline;
+
+impl Separator for Newline {
+    const SEPARATOR: char = '\n';
+}
+
+/// Read the field as a string, checking its content type and decoding it using its charset. Returns
+/// `None` if the field is treated as not sent, see [`TextConfig::empty_as_none`].
+async fn read_text(
+    req: &HttpRequest,
+    mut field: Field,
+    limits: &mut Limits,
+    config: &TextConfig,
+) -> Result<Option<String>, crate::Error> {
+    let field_name = field.name().to_owned();
+
+    if config.validate_content_type {
+        let valid = if let Some(mime) = field.content_type() {
+            mime.subtype() == mime::PLAIN || mime.suffix() == Some(mime::PLAIN)
+        } else {
+            // https://www.rfc-editor.org/rfc/rfc7578#section-4.4
+            // content type defaults to text/plain, so None should be considered valid
+            true
+        };
+        if !valid {
+            return Err(crate::Error::Field {
+                field_name,
+                source: config.map_error(req, TextError::ContentType),
+            });
+        }
+    }
+
+    let encoding = config.encoding(&field).map_err(|e| crate::Error::Field {
+        field_name: field_name.clone(),
+        source: config.map_error(req, e),
+    })?;
+
+    let options = config.options.or(field.text_options());
+    let bytes = Bytes::read_unscanned(req, &mut field, limits).await?;
+
+    let text = if encoding == UTF_8 {
+        String::from_utf8(bytes.data.into()).map_err(|e| crate::Error::Field {
+            field_name: field_name.clone(),
+            source: config.map_error(req, TextError::Utf8Error(e.utf8_error())),
+        })?
+    } else {
+        encoding
+            .decode_without_bom_handling_and_without_replacement(bytes.data.as_ref())
+            .map(Cow::into_owned)
+            .ok_or_else(|| crate::Error::Field {
+                field_name: field_name.clone(),
+                source: config.map_error(req, TextError::Decode(encoding.name())),
+            })?
+    };
+
+    let text = options.normalize(text).map_err(|e| crate::Error::Field {
+        field_name: field_name.clone(),
+        source: config.map_error(req, e),
+    })?;
+    if text.is_empty() && options.empty_as_none == Some(true) {
+        return Ok(None);
+    }
+    Ok(Some(text))
+}
+
+/// Options to normalise text after it is decoded, see [`TextConfig`].
+///
+/// These can be set for every field using the [`TextConfig`], or for a single field using the
+/// `#[multipart(...)]` attributes, see [`macro@crate::MultipartForm`]. An option that is `None`
+/// is not set, and so falls back to the [`TextConfig`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TextOptions {
+    pub trim: Option<bool>,
+    pub normalize_newlines: Option<bool>,
+    pub normalize_unicode: Option<Normalization>,
+    pub reject_control_chars: Option<bool>,
+    pub max_chars: Option<usize>,
+    pub empty_as_none: Option<bool>,
+}
+
+const DEFAULT_OPTIONS: TextOptions = TextOptions {
+    trim: None,
+    normalize_newlines: None,
+    normalize_unicode: None,
+    reject_control_chars: None,
+    max_chars: None,
+    empty_as_none: None,
+};
+
+impl TextOptions {
+    /// Override these options with any that are set in `other`.
+    fn or(self, other: Option<&TextOptions>) -> Self {
+        let Some(other) = other else {
+            return self;
+        };
+        TextOptions {
+            trim: other.trim.or(self.trim),
+            normalize_newlines: other.normalize_newlines.or(self.normalize_newlines),
+            normalize_unicode: other.normalize_unicode.or(self.normalize_unicode),
+            reject_control_chars: other.reject_control_chars.or(self.reject_control_chars),
+            max_chars: other.max_chars.or(self.max_chars),
+            empty_as_none: other.empty_as_none.or(self.empty_as_none),
+        }
+    }
+
+    /// Apply the options to the text, other than `empty_as_none`.
+    fn normalize(&self, mut text: String) -> Result<String, TextError> {
+        if self.normalize_newlines == Some(true) && text.contains('\r') {
+            text = text.replace("\r\n", "\n").replace('\r', "\n");
+        }
+        match self.normalize_unicode {
+            Some(Normalization::Nfc) => text = text.nfc().collect(),
+            Some(Normalization::Nfkc) => text = text.nfkc().collect(),
+            None => {}
+        }
+        if self.trim == Some(true) {
+            let trimmed = text.trim();
+            if trimmed.len() != text.len() {
+                text = trimmed.to_owned();
+            }
+        }
+        if self.reject_control_chars == Some(true)
+            && text
+                .chars()
+                .any(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r'))
+        {
+            return Err(TextError::ControlCharacter);
+        }
+        if let Some(max_chars) = self.max_chars {
+            if text.chars().nth(max_chars).is_some() {
+                return Err(TextError::TooManyChars(max_chars));
+            }
+        }
+        Ok(text)
+    }
+}
+
+/// A Unicode normalization form, see [`TextConfig::normalize_unicode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// Canonical composition, which combines characters such as `e` followed by a combining acute
+    /// accent into `é`.
+    Nfc,
+    /// Compatibility composition, which also replaces characters such as ligatures and full-width
+    /// forms with their plain equivalents.
+    Nfkc,
+}
+
 #[derive(Debug, Display, Error)]
 #[non_exhaustive]
 pub enum TextError {
@@ -80,6 +355,30 @@ pub enum TextError {
     /// Content type error
     #[display(fmt = "Content type error")]
     ContentType,
+
+    /// Decoding error, for a charset other than UTF-8
+    #[display(fmt = "{} decoding error", _0)]
+    Decode(#[error(not(source))] &'static str),
+
+    /// The charset is not supported, see [`TextConfig::strict_charset`]
+    #[display(fmt = "Unsupported charset: {}", _0)]
+    UnsupportedCharset(#[error(not(source))] String),
+
+    /// The [`FromStr`] implementation returned an error
+    #[display(fmt = "Plain text parse error: {}", _0)]
+    Parse(#[error(not(source))] String),
+
+    /// A [`Delimited`] field has more than the maximum number of items
+    #[display(fmt = "More than {} items", _0)]
+    TooManyItems(#[error(not(source))] usize),
+
+    /// The text contains a control character, see [`TextConfig::reject_control_chars`]
+    #[display(fmt = "Text contains a control character")]
+    ControlCharacter,
+
+    /// The text has more than the maximum number of characters, see [`TextConfig::max_chars`]
+    #[display(fmt = "Text is longer than {} characters", _0)]
+    TooManyChars(#[error(not(source))] usize),
 }
 
 impl ResponseError for TextError {
@@ -88,16 +387,24 @@ impl ResponseError for TextError {
     }
 }
 
-/// Configuration for the [`Text`] field reader.
+/// Configuration for the [`Text`], [`TextFromStr`] and [`Delimited`] field readers.
 #[derive(Clone)]
 pub struct TextConfig {
     err_handler: Option<Arc<dyn Fn(TextError, &HttpRequest) -> Error + Send + Sync>>,
     validate_content_type: bool,
+    default_charset: Option<&'static Encoding>,
+    strict_charset: bool,
+    max_items: usize,
+    options: TextOptions,
 }
 
 const DEFAULT_CONFIG: TextConfig = TextConfig {
     err_handler: None,
     validate_content_type: true,
+    default_charset: None,
+    strict_charset: false,
+    max_items: 1000,
+    options: DEFAULT_OPTIONS,
 };
 
 impl TextConfig {
@@ -132,6 +439,87 @@ impl TextConfig {
         self.validate_content_type = validate_content_type;
         self
     }
+
+    /// Sets the charset used when a field doesn't specify one, and the form has no `_charset_`
+    /// field. By default this is UTF-8.
+    pub fn default_charset(mut self, default_charset: &'static Encoding) -> Self {
+        self.default_charset = Some(default_charset);
+        self
+    }
+
+    /// Sets whether a field with an unsupported charset is rejected, rather than being decoded
+    /// using the default charset. By default this is false.
+    pub fn strict_charset(mut self, strict_charset: bool) -> Self {
+        self.strict_charset = strict_charset;
+        self
+    }
+
+    /// Sets the maximum number of items in a [`Delimited`] field, by default 1,000.
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = max_items;
+        self
+    }
+
+    /// Sets whether leading and trailing whitespace is removed, by default false.
+    pub fn trim(mut self, trim: bool) -> Self {
+        self.options.trim = Some(trim);
+        self
+    }
+
+    /// Sets whether `\r\n` and `\r` line endings are converted to `\n`, by default false.
+    pub fn normalize_newlines(mut self, normalize_newlines: bool) -> Self {
+        self.options.normalize_newlines = Some(normalize_newlines);
+        self
+    }
+
+    /// Sets the Unicode normalization form the text is converted to, by default the text is not
+    /// normalized.
+    pub fn normalize_unicode(mut self, normalization: Normalization) -> Self {
+        self.options.normalize_unicode = Some(normalization);
+        self
+    }
+
+    /// Sets whether text containing control characters, other than tabs and line endings, is
+    /// rejected, by default false.
+    pub fn reject_control_chars(mut self, reject_control_chars: bool) -> Self {
+        self.options.reject_control_chars = Some(reject_control_chars);
+        self
+    }
+
+    /// Sets the maximum number of characters (rather than bytes) in the text, after it has been
+    /// normalized. By default there is no limit, other than the form's size limits.
+    pub fn max_chars(mut self, max_chars: usize) -> Self {
+        self.options.max_chars = Some(max_chars);
+        self
+    }
+
+    /// Sets whether a field that is empty (after trimming, if enabled) is treated as if it was
+    /// not sent, by default false. An `Option<Text<T>>` field is then `None`, and a required field
+    /// is reported as missing.
+    pub fn empty_as_none(mut self, empty_as_none: bool) -> Self {
+        self.options.empty_as_none = Some(empty_as_none);
+        self
+    }
+
+    /// Get the encoding to decode the field with.
+    fn encoding(&self, field: &Field) -> Result<&'static Encoding, TextError> {
+        let default = self.default_charset.unwrap_or(UTF_8);
+        let charset = field
+            .content_type()
+            .and_then(|mime| mime.get_param(mime::CHARSET))
+            .map(|charset| charset.as_str())
+            .or_else(|| field.form_charset());
+        match charset {
+            Some(charset) => match Encoding::for_label(charset.as_bytes()) {
+                Some(encoding) => Ok(encoding),
+                None if self.strict_charset => {
+                    Err(TextError::UnsupportedCharset(charset.to_owned()))
+                }
+                None => Ok(default),
+            },
+            None => Ok(default),
+        }
+    }
 }
 
 impl Default for TextConfig {
@@ -143,12 +531,13 @@ impl Default for TextConfig {
 #[cfg(test)]
 mod tests {
     use crate::tests::send_form;
-    use crate::text::{Text, TextConfig};
+    use crate::text::{Delimited, Text, TextConfig, TextFromStr};
     use crate::MultipartForm;
     use actix_multipart_rfc7578::client::multipart;
     use actix_web::http::StatusCode;
     use actix_web::{web, App, HttpResponse, Responder};
     use std::io::Cursor;
+    use std::net::IpAddr;
 
     #[derive(MultipartForm)]
     struct TextForm {
@@ -182,4 +571,127 @@ mod tests {
         let response = send_form(&srv, form, "/").await;
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    #[derive(MultipartForm)]
+    struct CharsetForm {
+        field: Text<String>,
+    }
+
+    async fn test_charset_route(form: MultipartForm<CharsetForm>) -> impl Responder {
+        assert_eq!(form.field.as_str(), "café");
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_charset() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_charset_route))
+                .app_data(TextConfig::default().strict_charset(true))
+        });
+
+        // Charset from the field's content type
+        let bytes = Cursor::new(b"caf\xE9".to_vec());
+        let mime = "text/plain; charset=windows-1252".parse().unwrap();
+        let mut form = multipart::Form::default();
+        form.add_reader_file_with_mime("field", bytes, "", mime);
+        let response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Charset from the form's `_charset_` field
+        let bytes = Cursor::new(b"caf\xE9".to_vec());
+        let mut form = multipart::Form::default();
+        form.add_text("_charset_", "iso-8859-1");
+        form.add_reader_file_with_mime("field", bytes, "", mime::TEXT_PLAIN);
+        let response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Deny because the charset is unknown
+        let bytes = Cursor::new(b"caf\xE9".to_vec());
+        let mime = "text/plain; charset=unknown".parse().unwrap();
+        let mut form = multipart::Form::default();
+        form.add_reader_file_with_mime("field", bytes, "", mime);
+        let response = send_form(&srv, form, "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[derive(MultipartForm)]
+    struct FromStrForm {
+        address: TextFromStr<IpAddr>,
+        ids: Delimited<u32>,
+    }
+
+    async fn test_from_str_route(form: MultipartForm<FromStrForm>) -> impl Responder {
+        assert_eq!(form.address.to_string(), "127.0.0.1");
+        assert_eq!(*form.ids, vec![1, 2, 3]);
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_from_str() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_from_str_route))
+                .app_data(TextConfig::default().max_items(3))
+        });
+        let form = |address: &'static str, ids: &'static str| {
+            let mut form = multipart::Form::default();
+            form.add_text("address", address);
+            form.add_text("ids", ids);
+            form
+        };
+
+        let response = send_form(&srv, form("127.0.0.1", "1, 2,3"), "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send_form(&srv, form("localhost", "1,2,3"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = send_form(&srv, form("127.0.0.1", "1,two,3"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = send_form(&srv, form("127.0.0.1", "1,2,3,4"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[derive(MultipartForm)]
+    struct NormalizeForm {
+        #[multipart(max_chars = 8)]
+        name: Text<String>,
+        #[multipart(empty_as_none)]
+        comment: Option<Text<String>>,
+    }
+
+    async fn test_normalize_route(form: MultipartForm<NormalizeForm>) -> impl Responder {
+        assert_eq!(form.name.as_str(), "Jane\nDoe");
+        assert!(form.comment.is_none());
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_normalize() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_normalize_route))
+                .app_data(
+                    TextConfig::default()
+                        .trim(true)
+                        .normalize_newlines(true)
+                        .max_chars(4),
+                )
+        });
+        let form = |name: &'static str| {
+            let mut form = multipart::Form::default();
+            form.add_text("name", name);
+            form.add_text("comment", " ");
+            form
+        };
+
+        let response = send_form(&srv, form(" Jane\r\nDoe "), "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // The field's `max_chars` attribute overrides the config
+        let response = send_form(&srv, form("Jane Doe!"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
 }
@@ -0,0 +1,190 @@
+//! Deserializes a field as TOML, enabled using the `toml` feature.
+use crate::bytes::Bytes;
+use crate::{is_format, is_valid_content_type, Field, FieldReader, Limits};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, ResponseError};
+use derive_more::{Deref, DerefMut, Display, Error};
+use futures_core::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+
+/// Deserialize from [TOML](https://toml.io/).
+#[derive(Debug, Deref, DerefMut)]
+pub struct Toml<T: DeserializeOwned>(pub T);
+
+impl<T: DeserializeOwned> Toml<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<'t, T: DeserializeOwned + 'static> FieldReader<'t> for Toml<T> {
+    type Future = LocalBoxFuture<'t, Result<Self, crate::Error>>;
+
+    fn read_field(req: &'t HttpRequest, mut field: Field, limits: &'t mut Limits) -> Self::Future {
+        async move {
+            let config = TomlConfig::from_req(req);
+            let field_name = field.name().to_owned();
+
+            if !is_valid_content_type(&field, config.validate_content_type, |mime| {
+                is_format(mime, &["toml", "x-toml"], "toml")
+            }) {
+                return Err(crate::Error::Field {
+                    field_name,
+                    source: config.map_error(req, TomlFieldError::ContentType),
+                });
+            }
+
+            let bytes = Bytes::read_unscanned(req, &mut field, limits).await?;
+            let field_error = |err: TomlFieldError| crate::Error::Field {
+                field_name: field_name.clone(),
+                source: config.map_error(req, err),
+            };
+
+            let text = std::str::from_utf8(&bytes.data)
+                .map_err(|e| field_error(TomlFieldError::Utf8Error(e)))?;
+            Ok(Toml(toml::from_str(text).map_err(|e| {
+                field_error(TomlFieldError::Deserialize(e))
+            })?))
+        }
+        .boxed_local()
+    }
+}
+
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum TomlFieldError {
+    /// Deserialize error
+    #[display(fmt = "TOML deserialize error: {}", _0)]
+    Deserialize(toml::de::Error),
+
+    /// Utf8 error
+    #[display(fmt = "Utf8 decoding error: {}", _0)]
+    Utf8Error(std::str::Utf8Error),
+
+    /// Content type error
+    #[display(fmt = "Content type error")]
+    ContentType,
+}
+
+impl ResponseError for TomlFieldError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// Configuration for the [`Toml`] field reader.
+#[derive(Clone)]
+pub struct TomlConfig {
+    err_handler:
+        Option<Arc<dyn Fn(TomlFieldError, &HttpRequest) -> actix_web::Error + Send + Sync>>,
+    validate_content_type: bool,
+}
+
+const DEFAULT_CONFIG: TomlConfig = TomlConfig {
+    err_handler: None,
+    validate_content_type: true,
+};
+
+impl TomlConfig {
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(TomlFieldError, &HttpRequest) -> actix_web::Error + Send + Sync + 'static,
+    {
+        self.err_handler = Some(Arc::new(f));
+        self
+    }
+
+    /// Extract payload config from app data. Check both `T` and `Data<T>`, in that order, and fall
+    /// back to the default payload config.
+    fn from_req(req: &HttpRequest) -> &Self {
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<web::Data<Self>>().map(|d| d.as_ref()))
+            .unwrap_or(&DEFAULT_CONFIG)
+    }
+
+    fn map_error(&self, req: &HttpRequest, err: TomlFieldError) -> actix_web::Error {
+        if let Some(err_handler) = self.err_handler.as_ref() {
+            (*err_handler)(err, req)
+        } else {
+            err.into()
+        }
+    }
+
+    /// Sets whether or not the field must have a valid `Content-Type` header to be parsed, which
+    /// has the `toml` or `x-toml` subtype (e.g. `application/toml`) or the `+toml` suffix.
+    pub fn validate_content_type(mut self, validate_content_type: bool) -> Self {
+        self.validate_content_type = validate_content_type;
+        self
+    }
+}
+
+impl Default for TomlConfig {
+    fn default() -> Self {
+        DEFAULT_CONFIG
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::send_form;
+    use crate::toml::{Toml, TomlConfig};
+    use crate::MultipartForm;
+    use actix_multipart_rfc7578::client::multipart;
+    use actix_web::http::StatusCode;
+    use actix_web::{web, App, HttpResponse, Responder};
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    #[derive(MultipartForm)]
+    struct TomlForm {
+        metadata: Toml<HashMap<String, u32>>,
+    }
+
+    async fn test_toml_route(form: MultipartForm<TomlForm>) -> impl Responder {
+        assert_eq!(form.metadata["temperature"], 21);
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_toml() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_toml_route))
+                .app_data(TomlConfig::default())
+        });
+        let data = b"temperature = 21\n".to_vec();
+        let form = |data: Vec<u8>, mime: &str| {
+            let mut form = multipart::Form::default();
+            let mime = mime.parse().unwrap();
+            form.add_reader_file_with_mime("metadata", Cursor::new(data), "", mime);
+            form
+        };
+
+        let response = send_form(&srv, form(data.clone(), "application/toml"), "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Allow a structured syntax suffix
+        let response = send_form(
+            &srv,
+            form(data.clone(), "application/vnd.example+toml"),
+            "/",
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Deny because wrong content type
+        let response = send_form(&srv, form(data, "application/json"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Deny because invalid data
+        let response = send_form(
+            &srv,
+            form(b"temperature = [".to_vec(), "application/toml"),
+            "/",
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,248 @@
+//! Streaming decoding of a part's `Content-Transfer-Encoding`, see
+//! [RFC 2045 §6](https://www.rfc-editor.org/rfc/rfc2045#section-6).
+use crate::{Field, LimitKind, Limits, StreamLimitExceeded};
+use actix_http::error::PayloadError;
+use actix_multipart::MultipartError;
+use actix_web::error::ParseError;
+use actix_web::http::header::HeaderName;
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, Engine, GeneralPurpose, GeneralPurposeConfig};
+use bytes::{Bytes, BytesMut};
+use futures_core::stream::{LocalBoxStream, Stream};
+use futures_util::StreamExt;
+use std::cell::Cell;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{ready, Context, Poll};
+
+/// Base64 as used by MIME, where the padding may be omitted at the end of the data.
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Decode the field's data according to its `Content-Transfer-Encoding` header, if any, returning
+/// the number of encoded bytes read so far if the data is decoded.
+///
+/// The decoded data is consumed from the limits by the field reader as usual. As the encoded
+/// data may be larger (e.g. padded with whitespace), it is also checked against the total and
+/// field limits remaining at the start of the field, and the excess should be consumed once the
+/// field has been read.
+pub(crate) fn decode(
+    field: Field,
+    limits: &Limits,
+) -> Result<(Field, Option<Rc<Cell<usize>>>), MultipartError> {
+    let encoding = match field
+        .headers()
+        .get(HeaderName::from_static("content-transfer-encoding"))
+    {
+        Some(encoding) => encoding
+            .to_str()
+            .map_err(|_| ParseError::Header)?
+            .trim()
+            .to_ascii_lowercase(),
+        None => return Ok((field, None)),
+    };
+    let decoder = match encoding.as_str() {
+        "7bit" | "8bit" | "binary" => return Ok((field, None)),
+        "base64" => Decoder::Base64,
+        "quoted-printable" => Decoder::QuotedPrintable,
+        _ => return Err(ParseError::Header.into()),
+    };
+    let (kind, limit, remaining) = match limits.field_limit_remaining {
+        Some(remaining) if remaining < limits.total_limit_remaining => (
+            LimitKind::Field,
+            limits.field_limit.unwrap_or_default(),
+            remaining,
+        ),
+        _ => (
+            LimitKind::Total,
+            limits.total_limit,
+            limits.total_limit_remaining,
+        ),
+    };
+    let encoded_len = Rc::new(Cell::new(0));
+    let field_name = field.name().to_owned();
+    let field = field.decode_stream(|stream| TransferDecoder {
+        stream,
+        decoder,
+        pending: BytesMut::new(),
+        encoded_len: encoded_len.clone(),
+        encoded_limit: (kind, limit, remaining),
+        field_name,
+        done: false,
+    });
+    Ok((field, Some(encoded_len)))
+}
+
+#[derive(Clone, Copy)]
+enum Decoder {
+    Base64,
+    QuotedPrintable,
+}
+
+struct TransferDecoder {
+    stream: LocalBoxStream<'static, Result<Bytes, MultipartError>>,
+    decoder: Decoder,
+    /// Encoded data that can't be decoded until more data is received.
+    pending: BytesMut,
+    encoded_len: Rc<Cell<usize>>,
+    /// The limit that applies to the encoded data, its configured value, and the number of bytes
+    /// remaining at the start of the field.
+    encoded_limit: (LimitKind, usize, usize),
+    field_name: String,
+    done: bool,
+}
+
+impl Stream for TransferDecoder {
+    type Item = Result<Bytes, MultipartError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+            let decoded = match ready!(this.stream.poll_next_unpin(cx)) {
+                Some(Ok(chunk)) => {
+                    let encoded_len = this.encoded_len.get() + chunk.len();
+                    let (kind, limit, remaining) = this.encoded_limit;
+                    if encoded_len > remaining {
+                        let err = StreamLimitExceeded {
+                            kind,
+                            field_name: std::mem::take(&mut this.field_name),
+                            limit,
+                        };
+                        return Poll::Ready(Some(Err(err.into())));
+                    }
+                    this.encoded_len.set(encoded_len);
+                    this.pending.extend_from_slice(&chunk);
+                    this.decode(false)
+                }
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => {
+                    this.done = true;
+                    this.decode(true)
+                }
+            };
+            match decoded {
+                Ok(decoded) if decoded.is_empty() => {}
+                Ok(decoded) => return Poll::Ready(Some(Ok(decoded))),
+                Err(err) => return Poll::Ready(Some(Err(err))),
+            }
+        }
+    }
+}
+
+impl TransferDecoder {
+    /// Decode as much of the pending data as possible, at the end of the data everything must be
+    /// decoded.
+    fn decode(&mut self, end: bool) -> Result<Bytes, MultipartError> {
+        let decoded = match self.decoder {
+            Decoder::Base64 => decode_base64(&mut self.pending, end),
+            Decoder::QuotedPrintable => decode_quoted_printable(&mut self.pending, end),
+        };
+        decoded.ok_or_else(|| PayloadError::EncodingCorrupted.into())
+    }
+}
+
+/// Decode base64 data, ignoring any whitespace (i.e. line breaks).
+fn decode_base64(pending: &mut BytesMut, end: bool) -> Option<Bytes> {
+    if pending.iter().any(u8::is_ascii_whitespace) {
+        let data = pending
+            .iter()
+            .copied()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect::<Vec<_>>();
+        pending.clear();
+        pending.extend_from_slice(&data);
+    }
+    let len = if end {
+        pending.len()
+    } else {
+        pending.len() - pending.len() % 4
+    };
+    let encoded = pending.split_to(len);
+    BASE64.decode(encoded).ok().map(Bytes::from)
+}
+
+/// Decode quoted-printable data, see
+/// [RFC 2045 §6.7](https://www.rfc-editor.org/rfc/rfc2045#section-6.7).
+fn decode_quoted_printable(pending: &mut BytesMut, end: bool) -> Option<Bytes> {
+    let mut decoded = Vec::with_capacity(pending.len());
+    let mut i = 0;
+    while i < pending.len() {
+        if pending[i] != b'=' {
+            decoded.push(pending[i]);
+            i += 1;
+            continue;
+        }
+        match &pending[i + 1..] {
+            // A soft line break, which is also allowed at the end of the data
+            [] if end => i += 1,
+            [b'\n', ..] => i += 2,
+            [b'\r', b'\n', ..] => i += 3,
+            [hi, lo, ..] => {
+                let hex = [*hi, *lo];
+                let byte = std::str::from_utf8(&hex)
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())?;
+                decoded.push(byte);
+                i += 3;
+            }
+            // An incomplete escape sequence
+            _ if end => return None,
+            _ => break,
+        }
+    }
+    let _ = pending.split_to(i);
+    Some(Bytes::from(decoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transfer_encoding::{decode_base64, decode_quoted_printable};
+    use bytes::BytesMut;
+
+    /// Decode the data split into chunks of the given size.
+    fn decode(
+        data: &[u8],
+        chunk_size: usize,
+        f: fn(&mut BytesMut, bool) -> Option<bytes::Bytes>,
+    ) -> Option<Vec<u8>> {
+        let mut pending = BytesMut::new();
+        let mut decoded = Vec::new();
+        for chunk in data.chunks(chunk_size) {
+            pending.extend_from_slice(chunk);
+            decoded.extend_from_slice(&f(&mut pending, false)?);
+        }
+        decoded.extend_from_slice(&f(&mut pending, true)?);
+        Some(decoded)
+    }
+
+    #[test]
+    fn test_base64() {
+        let data = b"SGVsbG8s\r\nIHdvcmxk\r\nIQ==\r\n";
+        for chunk_size in 1..=data.len() {
+            assert_eq!(
+                decode(data, chunk_size, decode_base64).unwrap(),
+                b"Hello, world!"
+            );
+        }
+        assert_eq!(decode(b"SGk", 1, decode_base64).unwrap(), b"Hi");
+        assert!(decode(b"SGk*", 4, decode_base64).is_none());
+    }
+
+    #[test]
+    fn test_quoted_printable() {
+        let data = b"caf=C3=A9 =\r\nau lait=3D=\nyes=";
+        for chunk_size in 1..=data.len() {
+            assert_eq!(
+                decode(data, chunk_size, decode_quoted_printable).unwrap(),
+                "café au lait=yes".as_bytes()
+            );
+        }
+        assert!(decode(b"bad=ZZ", 6, decode_quoted_printable).is_none());
+        assert!(decode(b"short=A", 7, decode_quoted_printable).is_none());
+    }
+}
@@ -0,0 +1,37 @@
+//! Reads a form from an `application/x-www-form-urlencoded` body.
+use crate::{read_field, Error, Field, FormState, LimitKind, Limits, MultipartFormTrait};
+use actix_multipart::MultipartError;
+use actix_web::dev::Payload;
+use actix_web::HttpRequest;
+use bytes::{Bytes, BytesMut};
+use futures_util::TryStreamExt;
+
+/// Process each name/value pair of the body into the state, as if it were a text part of a
+/// multipart form.
+pub(crate) async fn read_form<T: MultipartFormTrait>(
+    req: &HttpRequest,
+    mut payload: Payload,
+    limits: &mut Limits,
+    form: &mut FormState,
+) -> Result<(), Error> {
+    // The body must be read in full before it can be decoded, the decoded values are then
+    // consumed from the limits by each field reader. The body is held in memory until every field
+    // has been read, so it is also charged to the memory limit.
+    let mut body = BytesMut::new();
+    while let Some(chunk) = payload.try_next().await.map_err(MultipartError::from)? {
+        if body.len() + chunk.len() > limits.total_limit_remaining {
+            return Err(limits.exceeded(LimitKind::Total, limits.total_limit));
+        }
+        limits.memory_limit_remaining = limits
+            .memory_limit_remaining
+            .checked_sub(chunk.len())
+            .ok_or_else(|| limits.exceeded(LimitKind::Memory, limits.memory_limit))?;
+        body.extend_from_slice(&chunk);
+    }
+
+    for (name, value) in form_urlencoded::parse(&body) {
+        let field = Field::from_urlencoded(name.into_owned(), Bytes::from(value.into_owned()));
+        read_field::<T>(req, field, limits, form).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,232 @@
+//! Deserializes a field as XML, enabled using the `xml` feature.
+use crate::bytes::Bytes;
+use crate::{is_format, is_valid_content_type, Field, FieldReader, Limits};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, ResponseError};
+use derive_more::{Deref, DerefMut, Display, Error};
+use futures_core::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use quick_xml::events::Event;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+
+/// Deserialize from [XML](https://www.w3.org/XML/), using [`quick_xml`].
+///
+/// Documents with a document type declaration (`<!DOCTYPE ...>`) are rejected by default, see
+/// [`XmlConfig::allow_doctype`]. Entities declared in a DTD are never expanded, so documents can't
+/// use them to include external files or to expand to an excessive size.
+#[derive(Debug, Deref, DerefMut)]
+pub struct Xml<T: DeserializeOwned>(pub T);
+
+impl<T: DeserializeOwned> Xml<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<'t, T: DeserializeOwned + 'static> FieldReader<'t> for Xml<T> {
+    type Future = LocalBoxFuture<'t, Result<Self, crate::Error>>;
+
+    fn read_field(req: &'t HttpRequest, mut field: Field, limits: &'t mut Limits) -> Self::Future {
+        async move {
+            let config = XmlConfig::from_req(req);
+            let field_name = field.name().to_owned();
+
+            if !is_valid_content_type(&field, config.validate_content_type, |mime| {
+                is_format(mime, &["xml"], "xml")
+            }) {
+                return Err(crate::Error::Field {
+                    field_name,
+                    source: config.map_error(req, XmlFieldError::ContentType),
+                });
+            }
+
+            let bytes = Bytes::read_unscanned(req, &mut field, limits).await?;
+            let field_error = |err: XmlFieldError| crate::Error::Field {
+                field_name: field_name.clone(),
+                source: config.map_error(req, err),
+            };
+
+            let text = std::str::from_utf8(&bytes.data)
+                .map_err(|e| field_error(XmlFieldError::Utf8Error(e)))?;
+            if !config.allow_doctype {
+                // A document that can't be read up to its root element is rejected, as it may
+                // still contain a declaration that could be processed
+                match has_doctype(text) {
+                    Ok(false) => {}
+                    Ok(true) => return Err(field_error(XmlFieldError::Doctype)),
+                    Err(e) => {
+                        return Err(field_error(XmlFieldError::Deserialize(
+                            quick_xml::DeError::InvalidXml(e),
+                        )))
+                    }
+                }
+            }
+            Ok(Xml(quick_xml::de::from_str(text)
+                .map_err(|e| field_error(XmlFieldError::Deserialize(e)))?))
+        }
+        .boxed_local()
+    }
+}
+
+/// Check whether the document has a document type declaration, which comes before the root
+/// element.
+fn has_doctype(text: &str) -> Result<bool, quick_xml::Error> {
+    let mut reader = quick_xml::Reader::from_str(text);
+    loop {
+        match reader.read_event()? {
+            Event::DocType(_) => return Ok(true),
+            Event::Decl(_) | Event::Comment(_) | Event::PI(_) | Event::Text(_) => continue,
+            _ => return Ok(false),
+        }
+    }
+}
+
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum XmlFieldError {
+    /// Deserialize error
+    #[display(fmt = "XML deserialize error: {}", _0)]
+    Deserialize(quick_xml::DeError),
+
+    /// Utf8 error
+    #[display(fmt = "Utf8 decoding error: {}", _0)]
+    Utf8Error(std::str::Utf8Error),
+
+    /// The document has a document type declaration, which is not allowed
+    #[display(fmt = "Document type declarations are not allowed")]
+    Doctype,
+
+    /// Content type error
+    #[display(fmt = "Content type error")]
+    ContentType,
+}
+
+impl ResponseError for XmlFieldError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// Configuration for the [`Xml`] field reader.
+#[derive(Clone)]
+pub struct XmlConfig {
+    err_handler: Option<Arc<dyn Fn(XmlFieldError, &HttpRequest) -> actix_web::Error + Send + Sync>>,
+    validate_content_type: bool,
+    allow_doctype: bool,
+}
+
+const DEFAULT_CONFIG: XmlConfig = XmlConfig {
+    err_handler: None,
+    validate_content_type: true,
+    allow_doctype: false,
+};
+
+impl XmlConfig {
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(XmlFieldError, &HttpRequest) -> actix_web::Error + Send + Sync + 'static,
+    {
+        self.err_handler = Some(Arc::new(f));
+        self
+    }
+
+    /// Extract payload config from app data. Check both `T` and `Data<T>`, in that order, and fall
+    /// back to the default payload config.
+    fn from_req(req: &HttpRequest) -> &Self {
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<web::Data<Self>>().map(|d| d.as_ref()))
+            .unwrap_or(&DEFAULT_CONFIG)
+    }
+
+    fn map_error(&self, req: &HttpRequest, err: XmlFieldError) -> actix_web::Error {
+        if let Some(err_handler) = self.err_handler.as_ref() {
+            (*err_handler)(err, req)
+        } else {
+            err.into()
+        }
+    }
+
+    /// Sets whether or not the field must have a valid `Content-Type` header to be parsed, which
+    /// is `application/xml`, `text/xml` or has the `+xml` suffix.
+    pub fn validate_content_type(mut self, validate_content_type: bool) -> Self {
+        self.validate_content_type = validate_content_type;
+        self
+    }
+
+    /// Sets whether or not documents may have a document type declaration, by default false. Any
+    /// entities that it declares are still not expanded, and using them is an error.
+    pub fn allow_doctype(mut self, allow_doctype: bool) -> Self {
+        self.allow_doctype = allow_doctype;
+        self
+    }
+}
+
+impl Default for XmlConfig {
+    fn default() -> Self {
+        DEFAULT_CONFIG
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::send_form;
+    use crate::xml::{Xml, XmlConfig};
+    use crate::MultipartForm;
+    use actix_multipart_rfc7578::client::multipart;
+    use actix_web::http::StatusCode;
+    use actix_web::{web, App, HttpResponse, Responder};
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    #[derive(MultipartForm)]
+    struct XmlForm {
+        metadata: Xml<HashMap<String, u32>>,
+    }
+
+    async fn test_xml_route(form: MultipartForm<XmlForm>) -> impl Responder {
+        assert_eq!(form.metadata["temperature"], 21);
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_xml() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_xml_route))
+                .app_data(XmlConfig::default())
+        });
+        let data = b"<metadata><temperature>21</temperature></metadata>".to_vec();
+        let form = |data: Vec<u8>, mime: &str| {
+            let mut form = multipart::Form::default();
+            let mime = mime.parse().unwrap();
+            form.add_reader_file_with_mime("metadata", Cursor::new(data), "", mime);
+            form
+        };
+
+        let response = send_form(&srv, form(data.clone(), "application/xml"), "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Deny because wrong content type
+        let response = send_form(&srv, form(data, "application/json"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Deny because invalid data
+        let data = b"<metadata><temperature>".to_vec();
+        let response = send_form(&srv, form(data, "application/xml"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Deny because of the entity declaration
+        let data = b"<!DOCTYPE metadata [<!ENTITY t \"21\">]>\
+            <metadata><temperature>&t;</temperature></metadata>"
+            .to_vec();
+        let response = send_form(&srv, form(data, "application/xml"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Deny because the declaration can't be read
+        let data =
+            b"<!DOCTYPE metadata [<metadata><temperature>21</temperature></metadata>".to_vec();
+        let response = send_form(&srv, form(data, "application/xml"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
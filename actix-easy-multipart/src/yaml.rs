@@ -0,0 +1,175 @@
+//! Deserializes a field as YAML, enabled using the `yaml` feature.
+use crate::bytes::Bytes;
+use crate::{is_format, is_valid_content_type, Field, FieldReader, Limits};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, ResponseError};
+use derive_more::{Deref, DerefMut, Display, Error};
+use futures_core::future::LocalBoxFuture;
+use futures_util::FutureExt;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+
+/// Deserialize from [YAML](https://yaml.org/), using [`serde_norway`] (a maintained fork of
+/// `serde_yaml`).
+#[derive(Debug, Deref, DerefMut)]
+pub struct Yaml<T: DeserializeOwned>(pub T);
+
+impl<T: DeserializeOwned> Yaml<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<'t, T: DeserializeOwned + 'static> FieldReader<'t> for Yaml<T> {
+    type Future = LocalBoxFuture<'t, Result<Self, crate::Error>>;
+
+    fn read_field(req: &'t HttpRequest, mut field: Field, limits: &'t mut Limits) -> Self::Future {
+        async move {
+            let config = YamlConfig::from_req(req);
+            let field_name = field.name().to_owned();
+
+            if !is_valid_content_type(&field, config.validate_content_type, |mime| {
+                is_format(mime, &["yaml", "x-yaml"], "yaml")
+            }) {
+                return Err(crate::Error::Field {
+                    field_name,
+                    source: config.map_error(req, YamlFieldError::ContentType),
+                });
+            }
+
+            let bytes = Bytes::read_unscanned(req, &mut field, limits).await?;
+
+            Ok(Yaml(
+                serde_norway::from_slice(bytes.data.as_ref()).map_err(|e| crate::Error::Field {
+                    field_name,
+                    source: config.map_error(req, YamlFieldError::Deserialize(e)),
+                })?,
+            ))
+        }
+        .boxed_local()
+    }
+}
+
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum YamlFieldError {
+    /// Deserialize error
+    #[display(fmt = "YAML deserialize error: {}", _0)]
+    Deserialize(serde_norway::Error),
+
+    /// Content type error
+    #[display(fmt = "Content type error")]
+    ContentType,
+}
+
+impl ResponseError for YamlFieldError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// Configuration for the [`Yaml`] field reader.
+#[derive(Clone)]
+pub struct YamlConfig {
+    err_handler:
+        Option<Arc<dyn Fn(YamlFieldError, &HttpRequest) -> actix_web::Error + Send + Sync>>,
+    validate_content_type: bool,
+}
+
+const DEFAULT_CONFIG: YamlConfig = YamlConfig {
+    err_handler: None,
+    validate_content_type: true,
+};
+
+impl YamlConfig {
+    pub fn error_handler<F>(mut self, f: F) -> Self
+    where
+        F: Fn(YamlFieldError, &HttpRequest) -> actix_web::Error + Send + Sync + 'static,
+    {
+        self.err_handler = Some(Arc::new(f));
+        self
+    }
+
+    /// Extract payload config from app data. Check both `T` and `Data<T>`, in that order, and fall
+    /// back to the default payload config.
+    fn from_req(req: &HttpRequest) -> &Self {
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<web::Data<Self>>().map(|d| d.as_ref()))
+            .unwrap_or(&DEFAULT_CONFIG)
+    }
+
+    fn map_error(&self, req: &HttpRequest, err: YamlFieldError) -> actix_web::Error {
+        if let Some(err_handler) = self.err_handler.as_ref() {
+            (*err_handler)(err, req)
+        } else {
+            err.into()
+        }
+    }
+
+    /// Sets whether or not the field must have a valid `Content-Type` header to be parsed, which
+    /// is `application/yaml`, `text/yaml` (or the `x-yaml` equivalents) or has the `+yaml` suffix.
+    pub fn validate_content_type(mut self, validate_content_type: bool) -> Self {
+        self.validate_content_type = validate_content_type;
+        self
+    }
+}
+
+impl Default for YamlConfig {
+    fn default() -> Self {
+        DEFAULT_CONFIG
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::send_form;
+    use crate::yaml::{Yaml, YamlConfig};
+    use crate::MultipartForm;
+    use actix_multipart_rfc7578::client::multipart;
+    use actix_web::http::StatusCode;
+    use actix_web::{web, App, HttpResponse, Responder};
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    #[derive(MultipartForm)]
+    struct YamlForm {
+        metadata: Yaml<HashMap<String, u32>>,
+    }
+
+    async fn test_yaml_route(form: MultipartForm<YamlForm>) -> impl Responder {
+        assert_eq!(form.metadata["temperature"], 21);
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn test_yaml() {
+        let srv = actix_test::start(|| {
+            App::new()
+                .route("/", web::post().to(test_yaml_route))
+                .app_data(YamlConfig::default())
+        });
+        let data = b"temperature: 21\n".to_vec();
+        let form = |data: Vec<u8>, mime: &str| {
+            let mut form = multipart::Form::default();
+            let mime = mime.parse().unwrap();
+            form.add_reader_file_with_mime("metadata", Cursor::new(data), "", mime);
+            form
+        };
+
+        let response = send_form(&srv, form(data.clone(), "application/yaml"), "/").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Deny because wrong content type
+        let response = send_form(&srv, form(data, "application/json"), "/").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // Deny because invalid data
+        let response = send_form(
+            &srv,
+            form(b"temperature: [".to_vec(), "application/yaml"),
+            "/",
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}